@@ -1,7 +1,7 @@
 use std::fs::File;
 use std::io::{BufReader, Read};
-use keyword_dataset_rs::err::{AppError, AppResult};
-use keyword_dataset_rs::content::keyword::{extract_keywords};
+use keyword_dataset_rs::err::AppResult;
+use keyword_dataset_rs::content::keyword::KeywordExtractor;
 
 fn main() -> AppResult<()> {
     let bytes = {
@@ -13,7 +13,7 @@ fn main() -> AppResult<()> {
     };
     let content = pdf_extract::extract_text_from_mem(&bytes)?;
     
-    let keywords = extract_keywords(&content)?;
+    let keywords = KeywordExtractor::new().extract_keywords(&content)?;
     println!("keywords:");
     for keyword in keywords {
         println!("{}", keyword);
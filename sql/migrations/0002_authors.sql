@@ -0,0 +1,18 @@
+CREATE TABLE IF NOT EXISTS author (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    last_name TEXT NOT NULL,
+    first_names TEXT NOT NULL DEFAULT '',
+    suffix TEXT NOT NULL DEFAULT '',
+    UNIQUE (last_name, first_names, suffix)
+);
+
+CREATE TABLE IF NOT EXISTS paper_author (
+    arxiv_id TEXT NOT NULL,
+    author_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (arxiv_id, position),
+    FOREIGN KEY (arxiv_id) REFERENCES arxiv_metadata(id),
+    FOREIGN KEY (author_id) REFERENCES author(id)
+);
+
+CREATE INDEX IF NOT EXISTS paper_author_author_id ON paper_author(author_id);
@@ -0,0 +1,176 @@
+CREATE TABLE IF NOT EXISTS category (
+    code TEXT PRIMARY KEY,
+    archive TEXT NOT NULL,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS paper_category (
+    arxiv_id TEXT NOT NULL,
+    category TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    is_primary INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (arxiv_id, category),
+    FOREIGN KEY (arxiv_id) REFERENCES arxiv_metadata(id)
+);
+
+CREATE INDEX IF NOT EXISTS paper_category_category ON paper_category(category, is_primary);
+
+-- arXiv category taxonomy, see https://arxiv.org/category_taxonomy
+INSERT OR REPLACE INTO category (code, archive, name) VALUES
+    ('astro-ph', 'astro-ph', 'Astrophysics'),
+    ('astro-ph.CO', 'astro-ph', 'Cosmology and Nongalactic Astrophysics'),
+    ('astro-ph.EP', 'astro-ph', 'Earth and Planetary Astrophysics'),
+    ('astro-ph.GA', 'astro-ph', 'Astrophysics of Galaxies'),
+    ('astro-ph.HE', 'astro-ph', 'High Energy Astrophysical Phenomena'),
+    ('astro-ph.IM', 'astro-ph', 'Instrumentation and Methods for Astrophysics'),
+    ('astro-ph.SR', 'astro-ph', 'Solar and Stellar Astrophysics'),
+    ('cond-mat', 'cond-mat', 'Condensed Matter'),
+    ('cond-mat.dis-nn', 'cond-mat', 'Disordered Systems and Neural Networks'),
+    ('cond-mat.mes-hall', 'cond-mat', 'Mesoscale and Nanoscale Physics'),
+    ('cond-mat.mtrl-sci', 'cond-mat', 'Materials Science'),
+    ('cond-mat.other', 'cond-mat', 'Other Condensed Matter'),
+    ('cond-mat.quant-gas', 'cond-mat', 'Quantum Gases'),
+    ('cond-mat.soft', 'cond-mat', 'Soft Condensed Matter'),
+    ('cond-mat.stat-mech', 'cond-mat', 'Statistical Mechanics'),
+    ('cond-mat.str-el', 'cond-mat', 'Strongly Correlated Electrons'),
+    ('cond-mat.supr-con', 'cond-mat', 'Superconductivity'),
+    ('cs.AI', 'cs', 'Artificial Intelligence'),
+    ('cs.AR', 'cs', 'Hardware Architecture'),
+    ('cs.CC', 'cs', 'Computational Complexity'),
+    ('cs.CE', 'cs', 'Computational Engineering, Finance, and Science'),
+    ('cs.CG', 'cs', 'Computational Geometry'),
+    ('cs.CL', 'cs', 'Computation and Language'),
+    ('cs.CR', 'cs', 'Cryptography and Security'),
+    ('cs.CV', 'cs', 'Computer Vision and Pattern Recognition'),
+    ('cs.CY', 'cs', 'Computers and Society'),
+    ('cs.DB', 'cs', 'Databases'),
+    ('cs.DC', 'cs', 'Distributed, Parallel, and Cluster Computing'),
+    ('cs.DL', 'cs', 'Digital Libraries'),
+    ('cs.DM', 'cs', 'Discrete Mathematics'),
+    ('cs.DS', 'cs', 'Data Structures and Algorithms'),
+    ('cs.ET', 'cs', 'Emerging Technologies'),
+    ('cs.FL', 'cs', 'Formal Languages and Automata Theory'),
+    ('cs.GL', 'cs', 'General Literature'),
+    ('cs.GR', 'cs', 'Graphics'),
+    ('cs.GT', 'cs', 'Computer Science and Game Theory'),
+    ('cs.HC', 'cs', 'Human-Computer Interaction'),
+    ('cs.IR', 'cs', 'Information Retrieval'),
+    ('cs.IT', 'cs', 'Information Theory'),
+    ('cs.LG', 'cs', 'Machine Learning'),
+    ('cs.LO', 'cs', 'Logic in Computer Science'),
+    ('cs.MA', 'cs', 'Multiagent Systems'),
+    ('cs.MM', 'cs', 'Multimedia'),
+    ('cs.MS', 'cs', 'Mathematical Software'),
+    ('cs.NA', 'cs', 'Numerical Analysis'),
+    ('cs.NE', 'cs', 'Neural and Evolutionary Computing'),
+    ('cs.NI', 'cs', 'Networking and Internet Architecture'),
+    ('cs.OH', 'cs', 'Other Computer Science'),
+    ('cs.OS', 'cs', 'Operating Systems'),
+    ('cs.PF', 'cs', 'Performance'),
+    ('cs.PL', 'cs', 'Programming Languages'),
+    ('cs.RO', 'cs', 'Robotics'),
+    ('cs.SC', 'cs', 'Symbolic Computation'),
+    ('cs.SD', 'cs', 'Sound'),
+    ('cs.SE', 'cs', 'Software Engineering'),
+    ('cs.SI', 'cs', 'Social and Information Networks'),
+    ('cs.SY', 'cs', 'Systems and Control'),
+    ('econ.EM', 'econ', 'Econometrics'),
+    ('econ.GN', 'econ', 'General Economics'),
+    ('econ.TH', 'econ', 'Theoretical Economics'),
+    ('eess.AS', 'eess', 'Audio and Speech Processing'),
+    ('eess.IV', 'eess', 'Image and Video Processing'),
+    ('eess.SP', 'eess', 'Signal Processing'),
+    ('eess.SY', 'eess', 'Systems and Control'),
+    ('gr-qc', 'gr-qc', 'General Relativity and Quantum Cosmology'),
+    ('hep-ex', 'hep-ex', 'High Energy Physics - Experiment'),
+    ('hep-lat', 'hep-lat', 'High Energy Physics - Lattice'),
+    ('hep-ph', 'hep-ph', 'High Energy Physics - Phenomenology'),
+    ('hep-th', 'hep-th', 'High Energy Physics - Theory'),
+    ('math-ph', 'math-ph', 'Mathematical Physics'),
+    ('math.AC', 'math', 'Commutative Algebra'),
+    ('math.AG', 'math', 'Algebraic Geometry'),
+    ('math.AP', 'math', 'Analysis of PDEs'),
+    ('math.AT', 'math', 'Algebraic Topology'),
+    ('math.CA', 'math', 'Classical Analysis and ODEs'),
+    ('math.CO', 'math', 'Combinatorics'),
+    ('math.CT', 'math', 'Category Theory'),
+    ('math.CV', 'math', 'Complex Variables'),
+    ('math.DG', 'math', 'Differential Geometry'),
+    ('math.DS', 'math', 'Dynamical Systems'),
+    ('math.FA', 'math', 'Functional Analysis'),
+    ('math.GM', 'math', 'General Mathematics'),
+    ('math.GN', 'math', 'General Topology'),
+    ('math.GR', 'math', 'Group Theory'),
+    ('math.GT', 'math', 'Geometric Topology'),
+    ('math.HO', 'math', 'History and Overview'),
+    ('math.IT', 'math', 'Information Theory'),
+    ('math.KT', 'math', 'K-Theory and Homology'),
+    ('math.LO', 'math', 'Logic'),
+    ('math.MG', 'math', 'Metric Geometry'),
+    ('math.MP', 'math', 'Mathematical Physics'),
+    ('math.NA', 'math', 'Numerical Analysis'),
+    ('math.NT', 'math', 'Number Theory'),
+    ('math.OA', 'math', 'Operator Algebras'),
+    ('math.OC', 'math', 'Optimization and Control'),
+    ('math.PR', 'math', 'Probability'),
+    ('math.QA', 'math', 'Quantum Algebra'),
+    ('math.RA', 'math', 'Rings and Algebras'),
+    ('math.RT', 'math', 'Representation Theory'),
+    ('math.SG', 'math', 'Symplectic Geometry'),
+    ('math.SP', 'math', 'Spectral Theory'),
+    ('math.ST', 'math', 'Statistics Theory'),
+    ('nlin.AO', 'nlin', 'Adaptation and Self-Organizing Systems'),
+    ('nlin.CD', 'nlin', 'Chaotic Dynamics'),
+    ('nlin.CG', 'nlin', 'Cellular Automata and Lattice Gases'),
+    ('nlin.PS', 'nlin', 'Pattern Formation and Solitons'),
+    ('nlin.SI', 'nlin', 'Exactly Solvable and Integrable Systems'),
+    ('nucl-ex', 'nucl-ex', 'Nuclear Experiment'),
+    ('nucl-th', 'nucl-th', 'Nuclear Theory'),
+    ('physics.acc-ph', 'physics', 'Accelerator Physics'),
+    ('physics.ao-ph', 'physics', 'Atmospheric and Oceanic Physics'),
+    ('physics.app-ph', 'physics', 'Applied Physics'),
+    ('physics.atm-clus', 'physics', 'Atomic and Molecular Clusters'),
+    ('physics.atom-ph', 'physics', 'Atomic Physics'),
+    ('physics.bio-ph', 'physics', 'Biological Physics'),
+    ('physics.chem-ph', 'physics', 'Chemical Physics'),
+    ('physics.class-ph', 'physics', 'Classical Physics'),
+    ('physics.comp-ph', 'physics', 'Computational Physics'),
+    ('physics.data-an', 'physics', 'Data Analysis, Statistics and Probability'),
+    ('physics.ed-ph', 'physics', 'Physics Education'),
+    ('physics.flu-dyn', 'physics', 'Fluid Dynamics'),
+    ('physics.gen-ph', 'physics', 'General Physics'),
+    ('physics.geo-ph', 'physics', 'Geophysics'),
+    ('physics.hist-ph', 'physics', 'History and Philosophy of Physics'),
+    ('physics.ins-det', 'physics', 'Instrumentation and Detectors'),
+    ('physics.med-ph', 'physics', 'Medical Physics'),
+    ('physics.optics', 'physics', 'Optics'),
+    ('physics.plasm-ph', 'physics', 'Plasma Physics'),
+    ('physics.pop-ph', 'physics', 'Popular Physics'),
+    ('physics.soc-ph', 'physics', 'Physics and Society'),
+    ('physics.space-ph', 'physics', 'Space Physics'),
+    ('q-bio.BM', 'q-bio', 'Biomolecules'),
+    ('q-bio.CB', 'q-bio', 'Cell Behavior'),
+    ('q-bio.GN', 'q-bio', 'Genomics'),
+    ('q-bio.MN', 'q-bio', 'Molecular Networks'),
+    ('q-bio.NC', 'q-bio', 'Neurons and Cognition'),
+    ('q-bio.OT', 'q-bio', 'Other Quantitative Biology'),
+    ('q-bio.PE', 'q-bio', 'Populations and Evolution'),
+    ('q-bio.QM', 'q-bio', 'Quantitative Methods'),
+    ('q-bio.SC', 'q-bio', 'Subcellular Processes'),
+    ('q-bio.TO', 'q-bio', 'Tissues and Organs'),
+    ('q-fin.CP', 'q-fin', 'Computational Finance'),
+    ('q-fin.EC', 'q-fin', 'Economics'),
+    ('q-fin.GN', 'q-fin', 'General Finance'),
+    ('q-fin.MF', 'q-fin', 'Mathematical Finance'),
+    ('q-fin.PM', 'q-fin', 'Portfolio Management'),
+    ('q-fin.PR', 'q-fin', 'Pricing of Securities'),
+    ('q-fin.RM', 'q-fin', 'Risk Management'),
+    ('q-fin.ST', 'q-fin', 'Statistical Finance'),
+    ('q-fin.TR', 'q-fin', 'Trading and Market Microstructure'),
+    ('quant-ph', 'quant-ph', 'Quantum Physics'),
+    ('stat.AP', 'stat', 'Applications'),
+    ('stat.CO', 'stat', 'Computation'),
+    ('stat.ME', 'stat', 'Methodology'),
+    ('stat.ML', 'stat', 'Machine Learning'),
+    ('stat.OT', 'stat', 'Other Statistics'),
+    ('stat.TH', 'stat', 'Statistics Theory');
@@ -0,0 +1,5 @@
+-- typed timestamps; created_utc is backfilled from the RFC 2822 `created` strings after this runs
+ALTER TABLE arxiv_version ADD COLUMN created_utc TEXT;
+ALTER TABLE arxiv_metadata ADD COLUMN update_date TEXT;
+
+CREATE INDEX IF NOT EXISTS arxiv_version_created_utc ON arxiv_version(arxiv_id, created_utc);
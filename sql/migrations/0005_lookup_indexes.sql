@@ -0,0 +1,3 @@
+CREATE INDEX IF NOT EXISTS arxiv_version_arxiv_id ON arxiv_version(arxiv_id, version);
+
+CREATE INDEX IF NOT EXISTS paper_data_arxiv_id ON paper_data(arxiv_id);
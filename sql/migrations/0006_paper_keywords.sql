@@ -0,0 +1,11 @@
+-- keywords as rows; existing comma-joined strings are split into this table after it runs
+CREATE TABLE paper_keyword (
+    arxiv_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    keyword TEXT NOT NULL,
+    normalized TEXT NOT NULL,
+    PRIMARY KEY (arxiv_id, position),
+    FOREIGN KEY (arxiv_id) REFERENCES arxiv_metadata(id)
+);
+
+CREATE INDEX paper_keyword_normalized ON paper_keyword(normalized);
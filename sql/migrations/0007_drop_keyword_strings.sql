@@ -0,0 +1,25 @@
+-- keywords now live in paper_keyword; the view returns them as a JSON array in position order
+DROP VIEW training_data;
+
+ALTER TABLE paper_data DROP COLUMN keywords;
+
+CREATE VIEW training_data AS
+SELECT
+    paper_data.arxiv_id,
+    paper_data.content,
+    paper_data.abstract,
+    (
+        SELECT json_group_array(keyword)
+        FROM (
+            SELECT keyword FROM paper_keyword
+            WHERE paper_keyword.arxiv_id = paper_data.arxiv_id
+            ORDER BY position
+        )
+    ) AS keywords
+FROM paper_data
+INNER JOIN extraction_result ON paper_data.arxiv_id = extraction_result.arxiv_id
+WHERE
+    extraction_result.status_code = 'OK' AND
+    LENGTH(paper_data.content) > 0 AND
+    LENGTH(paper_data.abstract) > 0 AND
+    EXISTS (SELECT 1 FROM paper_keyword WHERE paper_keyword.arxiv_id = paper_data.arxiv_id);
@@ -0,0 +1,10 @@
+-- which extraction logic produced each row, so rows from outdated logic can be re-run
+ALTER TABLE paper_data ADD COLUMN keyword_strategy TEXT;
+ALTER TABLE paper_data ADD COLUMN body_start_pattern TEXT;
+ALTER TABLE paper_data ADD COLUMN body_end_pattern TEXT;
+ALTER TABLE paper_data ADD COLUMN extractor_version TEXT;
+ALTER TABLE paper_data ADD COLUMN extracted_utc TEXT;
+ALTER TABLE paper_data ADD COLUMN source_object TEXT;
+ALTER TABLE paper_data ADD COLUMN source_generation INTEGER;
+
+CREATE INDEX paper_data_keyword_strategy ON paper_data(keyword_strategy, extractor_version);
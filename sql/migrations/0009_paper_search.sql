@@ -0,0 +1,67 @@
+-- full-text index over each paper_data row, keyed by paper_data.id and kept in sync by triggers
+CREATE VIRTUAL TABLE paper_search USING fts5(
+    arxiv_id UNINDEXED,
+    title,
+    abstract,
+    content,
+    keywords,
+    tokenize = 'porter unicode61'
+);
+
+CREATE TRIGGER paper_data_search_insert AFTER INSERT ON paper_data BEGIN
+    INSERT INTO paper_search (rowid, arxiv_id, title, abstract, content, keywords)
+    VALUES (
+        NEW.id,
+        NEW.arxiv_id,
+        (SELECT title FROM arxiv_metadata WHERE id = NEW.arxiv_id),
+        NEW.abstract,
+        NEW.content,
+        (
+            SELECT group_concat(keyword, '; ')
+            FROM (SELECT keyword FROM paper_keyword WHERE arxiv_id = NEW.arxiv_id ORDER BY position)
+        )
+    );
+END;
+
+CREATE TRIGGER paper_data_search_update AFTER UPDATE OF abstract, content ON paper_data BEGIN
+    UPDATE paper_search SET abstract = NEW.abstract, content = NEW.content WHERE rowid = NEW.id;
+END;
+
+CREATE TRIGGER paper_data_search_delete AFTER DELETE ON paper_data BEGIN
+    DELETE FROM paper_search WHERE rowid = OLD.id;
+END;
+
+CREATE TRIGGER arxiv_metadata_search_update AFTER UPDATE OF title ON arxiv_metadata BEGIN
+    UPDATE paper_search SET title = NEW.title
+    WHERE rowid IN (SELECT id FROM paper_data WHERE arxiv_id = NEW.id);
+END;
+
+CREATE TRIGGER paper_keyword_search_insert AFTER INSERT ON paper_keyword BEGIN
+    UPDATE paper_search SET keywords = (
+        SELECT group_concat(keyword, '; ')
+        FROM (SELECT keyword FROM paper_keyword WHERE arxiv_id = NEW.arxiv_id ORDER BY position)
+    )
+    WHERE rowid IN (SELECT id FROM paper_data WHERE arxiv_id = NEW.arxiv_id);
+END;
+
+CREATE TRIGGER paper_keyword_search_delete AFTER DELETE ON paper_keyword BEGIN
+    UPDATE paper_search SET keywords = (
+        SELECT group_concat(keyword, '; ')
+        FROM (SELECT keyword FROM paper_keyword WHERE arxiv_id = OLD.arxiv_id ORDER BY position)
+    )
+    WHERE rowid IN (SELECT id FROM paper_data WHERE arxiv_id = OLD.arxiv_id);
+END;
+
+INSERT INTO paper_search (rowid, arxiv_id, title, abstract, content, keywords)
+SELECT
+    paper_data.id,
+    paper_data.arxiv_id,
+    arxiv_metadata.title,
+    paper_data.abstract,
+    paper_data.content,
+    (
+        SELECT group_concat(keyword, '; ')
+        FROM (SELECT keyword FROM paper_keyword WHERE arxiv_id = paper_data.arxiv_id ORDER BY position)
+    )
+FROM paper_data
+LEFT JOIN arxiv_metadata ON arxiv_metadata.id = paper_data.arxiv_id;
@@ -0,0 +1,15 @@
+-- full PDF text per paper version, compressed, so extraction can be re-run without downloading
+CREATE TABLE paper_text (
+    arxiv_id TEXT NOT NULL,
+    -- 0 when the version of the PDF is unknown
+    version INTEGER NOT NULL,
+    compression TEXT NOT NULL,
+    text BLOB NOT NULL,
+    -- uncompressed length in bytes
+    text_bytes INTEGER NOT NULL,
+    source_object TEXT,
+    source_generation INTEGER,
+    stored_utc TEXT NOT NULL,
+    PRIMARY KEY (arxiv_id, version),
+    FOREIGN KEY (arxiv_id) REFERENCES arxiv_metadata(id)
+);
@@ -0,0 +1,35 @@
+-- every extraction attempt per paper, so papers that failed can be retried without losing history
+CREATE TABLE extraction_attempt (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    arxiv_id TEXT NOT NULL,
+    -- 1 for the first attempt on a paper
+    attempt INTEGER NOT NULL,
+    status_code VARCHAR(32),
+    status_msg TEXT,
+    -- NULL for results recorded before attempts were tracked
+    attempted_utc TEXT,
+    duration_ms INTEGER,
+    UNIQUE (arxiv_id, attempt)
+);
+
+CREATE INDEX extraction_attempt_status_code ON extraction_attempt(status_code);
+
+INSERT INTO extraction_attempt (arxiv_id, attempt, status_code, status_msg)
+SELECT arxiv_id, 1, status_code, status_msg FROM extraction_result;
+
+DROP TABLE extraction_result;
+
+-- the latest attempt per paper, under the name of the table it replaces
+CREATE VIEW extraction_result AS
+SELECT
+    arxiv_id,
+    status_code,
+    status_msg,
+    attempt AS attempts,
+    attempted_utc,
+    duration_ms
+FROM extraction_attempt
+WHERE attempt = (
+    SELECT MAX(latest.attempt) FROM extraction_attempt AS latest
+    WHERE latest.arxiv_id = extraction_attempt.arxiv_id
+);
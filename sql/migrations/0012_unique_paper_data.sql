@@ -0,0 +1,4 @@
+-- one paper_data row per paper. Nothing used to stop reloads from adding a second row; the
+-- migration refuses to run while any are left, and `db check --repair` removes them.
+DROP INDEX IF EXISTS paper_data_arxiv_id;
+CREATE UNIQUE INDEX paper_data_arxiv_id ON paper_data(arxiv_id);
@@ -0,0 +1,24 @@
+-- index of a PDF cache directory. Objects are stored once per content hash; entries map paper
+-- versions onto them.
+CREATE TABLE IF NOT EXISTS cache_object (
+    -- hex MD5 of the PDF, which is also its file name
+    md5 TEXT PRIMARY KEY,
+    size INTEGER NOT NULL,
+    stored_utc TEXT NOT NULL,
+    -- unix milliseconds, for least-recently-used eviction
+    last_used_ms INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS cache_object_last_used ON cache_object(last_used_ms);
+
+CREATE TABLE IF NOT EXISTS cache_entry (
+    arxiv_id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    md5 TEXT NOT NULL,
+    object_name TEXT,
+    generation INTEGER,
+    PRIMARY KEY (arxiv_id, version),
+    FOREIGN KEY (md5) REFERENCES cache_object(md5) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS cache_entry_md5 ON cache_entry(md5);
@@ -0,0 +1,490 @@
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, SystemTime};
+use base64::Engine;
+use chrono::Utc;
+use log::{debug, info, warn};
+use md5::{Digest, Md5};
+use rand::Rng;
+use rusqlite::{named_params, Connection, OptionalExtension};
+use crate::err::{AppError, AppResult};
+use crate::metadata::to_iso8601_utc;
+
+const INDEX_FILE: &str = "index.sqlite";
+/// WAL needs shared memory between every process using the index, which only works on one host.
+/// A rollback journal only relies on file locks, so the cache can live on a shared volume.
+const INDEX_JOURNAL_MODE: &str = "DELETE";
+const OBJECTS_DIR: &str = "objects";
+/// partially written objects older than this are assumed to be abandoned
+const STALE_TEMP_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// a paper version stored in the cache
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    /// unversioned arxiv id
+    pub arxiv_id: String,
+    /// 0 when the version is unknown
+    pub version: u32,
+    /// hex MD5 of the PDF
+    pub md5: String,
+    /// bucket object the PDF was downloaded from
+    pub object_name: Option<String>,
+    pub generation: Option<i64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheStats {
+    pub objects: u64,
+    pub entries: u64,
+    pub total_bytes: u64,
+    pub oldest_use_ms: Option<i64>,
+    pub newest_use_ms: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct VerifyReport {
+    pub checked: u64,
+    /// indexed objects whose file is gone
+    pub missing: Vec<String>,
+    /// indexed objects whose file doesn't hash to its name
+    pub corrupt: Vec<String>,
+    /// whether missing and corrupt objects were dropped from the cache
+    pub repaired: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    pub evicted_objects: u64,
+    pub evicted_bytes: u64,
+    /// files in the object directory that the index doesn't know about
+    pub removed_orphans: u64,
+}
+
+/// A directory of downloaded PDFs, stored once per MD5 so repeat runs, and teammates sharing the
+/// directory over a network volume, skip downloads. An SQLite index maps paper versions to objects and tracks last use,
+/// so the directory can be kept under a size limit by evicting the least recently used objects.
+pub struct PdfCache {
+    dir: PathBuf,
+    index: Mutex<Connection>,
+    /// evict down to this many bytes after each insert, if set
+    max_bytes: Option<u64>,
+}
+
+impl PdfCache {
+    /// opens the cache in `dir`, creating it if needed
+    pub fn open<PathT: AsRef<Path>>(dir: PathT) -> AppResult<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(dir.join(OBJECTS_DIR))?;
+
+        let conn = Connection::open(dir.join(INDEX_FILE))?;
+        conn.busy_timeout(Duration::from_secs(30))?;
+        let journal_mode = conn.pragma_update_and_check(None, "journal_mode", INDEX_JOURNAL_MODE, |row| row.get::<_, String>(0))?;
+        if !journal_mode.eq_ignore_ascii_case(INDEX_JOURNAL_MODE) {
+            // a cache index created in WAL mode only converts once nobody else has it open
+            warn!("PDF cache index is in {} mode; sharing it across hosts is unsafe until it converts", journal_mode);
+        }
+        conn.pragma_update(None, "foreign_keys", true)?;
+        conn.execute_batch(include_str!("../sql/pdf_cache.sql"))?;
+
+        Ok(Self {
+            dir,
+            index: Mutex::new(conn),
+            max_bytes: None,
+        })
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: Option<u64>) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// the cached entry for a paper version, without touching the network
+    pub fn lookup(&self, arxiv_id: &str, version: u32) -> AppResult<Option<CacheEntry>> {
+        self.lock().query_row(
+            "SELECT arxiv_id, version, md5, object_name, generation FROM cache_entry WHERE arxiv_id = :arxiv_id AND version = :version",
+            named_params! { ":arxiv_id": arxiv_id, ":version": version },
+            |row| Ok(CacheEntry {
+                arxiv_id: row.get(0)?,
+                version: row.get(1)?,
+                md5: row.get(2)?,
+                object_name: row.get(3)?,
+                generation: row.get(4)?,
+            })
+        ).optional().map_err(Into::into)
+    }
+
+    /// reads the object with the entry's MD5, recording the entry and its use on a hit
+    pub fn get(&self, entry: &CacheEntry) -> AppResult<Option<Vec<u8>>> {
+        let known = self.lock()
+            .query_row("SELECT 1 FROM cache_object WHERE md5 = :md5", named_params! { ":md5": entry.md5 }, |_| Ok(()))
+            .optional()?
+            .is_some();
+        if !known {
+            return Ok(None);
+        }
+
+        match fs::read(self.object_path(&entry.md5)) {
+            Ok(bytes) => {
+                let index = self.lock();
+                touch_object(&index, &entry.md5)?;
+                upsert_entry(&index, entry)?;
+                Ok(Some(bytes))
+            }
+            // evicted by another process since we looked
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                debug!("cache object {} vanished, treating as a miss", entry.md5);
+                self.lock().execute("DELETE FROM cache_object WHERE md5 = :md5", named_params! { ":md5": entry.md5 })?;
+                Ok(None)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// stores a downloaded PDF, refusing bytes that don't match the entry's MD5
+    pub fn put(&self, entry: &CacheEntry, bytes: &[u8]) -> AppResult<()> {
+        let found = md5_hex(bytes);
+        if found != entry.md5 {
+            return Err(AppError::ChecksumMismatch {
+                object: entry.object_name.clone().unwrap_or_else(|| entry.arxiv_id.clone()),
+                expected: entry.md5.clone(),
+                found,
+            });
+        }
+
+        // write under a temporary name first, so readers never see a partial object
+        let path = self.object_path(&entry.md5);
+        fs::create_dir_all(path.parent().expect("object path has a parent"))?;
+        let temp_path = path.with_extension(format!("tmp-{:016x}", rand::rng().random::<u64>()));
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, &path)?;
+
+        {
+            let index = self.lock();
+            index.execute(
+                "INSERT INTO cache_object (md5, size, stored_utc, last_used_ms) VALUES (:md5, :size, :stored_utc, :now)
+                 ON CONFLICT (md5) DO UPDATE SET last_used_ms = excluded.last_used_ms",
+                named_params! {
+                    ":md5": entry.md5,
+                    ":size": bytes.len() as u64,
+                    ":stored_utc": to_iso8601_utc(&Utc::now()),
+                    ":now": now_ms(),
+                }
+            )?;
+            upsert_entry(&index, entry)?;
+        }
+
+        if let Some(max_bytes) = self.max_bytes {
+            self.evict_to(max_bytes)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn stats(&self) -> AppResult<CacheStats> {
+        let index = self.lock();
+        let (objects, total_bytes, oldest_use_ms, newest_use_ms) = index.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size), 0), MIN(last_used_ms), MAX(last_used_ms) FROM cache_object",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        )?;
+        let entries = index.query_row("SELECT COUNT(*) FROM cache_entry", [], |row| row.get(0))?;
+
+        Ok(CacheStats {
+            objects,
+            entries,
+            total_bytes,
+            oldest_use_ms,
+            newest_use_ms,
+        })
+    }
+
+    /// re-hashes every indexed object. With `repair`, objects that are missing or corrupt are
+    /// dropped from the cache so they get downloaded again.
+    pub fn verify(&self, repair: bool) -> AppResult<VerifyReport> {
+        let md5s = self.lock()
+            .prepare("SELECT md5 FROM cache_object ORDER BY md5")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut report = VerifyReport {
+            repaired: repair,
+            ..Default::default()
+        };
+        for md5 in md5s {
+            report.checked += 1;
+            match fs::read(self.object_path(&md5)) {
+                Ok(bytes) if md5_hex(&bytes) == md5 => continue,
+                Ok(_) => report.corrupt.push(md5),
+                Err(err) if err.kind() == ErrorKind::NotFound => report.missing.push(md5),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if repair {
+            for md5 in report.missing.iter().chain(&report.corrupt) {
+                self.remove_object(md5)?;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// evicts least recently used objects until the cache fits in `max_bytes`, and deletes files
+    /// the index doesn't know about
+    pub fn prune(&self, max_bytes: u64) -> AppResult<PruneReport> {
+        let mut report = self.evict_to(max_bytes)?;
+        report.removed_orphans = self.remove_orphans()?;
+        Ok(report)
+    }
+
+    fn evict_to(&self, max_bytes: u64) -> AppResult<PruneReport> {
+        let mut report = PruneReport::default();
+        let mut total_bytes = self.stats()?.total_bytes;
+        if total_bytes <= max_bytes {
+            return Ok(report);
+        }
+
+        let candidates = self.lock()
+            .prepare("SELECT md5, size FROM cache_object ORDER BY last_used_ms")?
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u64>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (md5, size) in candidates {
+            if total_bytes <= max_bytes {
+                break;
+            }
+
+            self.remove_object(&md5)?;
+            total_bytes = total_bytes.saturating_sub(size);
+            report.evicted_objects += 1;
+            report.evicted_bytes += size;
+        }
+
+        info!("evicted {} cached PDF(s), {} byte(s)", report.evicted_objects, report.evicted_bytes);
+        Ok(report)
+    }
+
+    /// drops an object and its entries from the index, then deletes its file
+    fn remove_object(&self, md5: &str) -> AppResult<()> {
+        self.lock().execute("DELETE FROM cache_object WHERE md5 = :md5", named_params! { ":md5": md5 })?;
+        match fs::remove_file(self.object_path(md5)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn remove_orphans(&self) -> AppResult<u64> {
+        let mut removed = 0u64;
+        for shard in fs::read_dir(self.dir.join(OBJECTS_DIR))? {
+            let shard = shard?;
+            if !shard.file_type()?.is_dir() {
+                continue;
+            }
+
+            for file in fs::read_dir(shard.path())? {
+                let path = file?.path();
+                let is_orphan = match path.extension().and_then(|extension| extension.to_str()) {
+                    Some("pdf") => {
+                        let md5 = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default();
+                        !self.is_indexed(md5)?
+                    }
+                    Some(extension) if extension.starts_with("tmp-") => is_stale(&path)?,
+                    _ => false,
+                };
+
+                if is_orphan {
+                    warn!("removing orphaned cache file {}", path.display());
+                    fs::remove_file(&path)?;
+                    removed += 1;
+                }
+            }
+        }
+
+        Ok(removed)
+    }
+
+    fn is_indexed(&self, md5: &str) -> AppResult<bool> {
+        self.lock()
+            .query_row("SELECT EXISTS(SELECT 1 FROM cache_object WHERE md5 = :md5)", named_params! { ":md5": md5 }, |row| row.get(0))
+            .map_err(Into::into)
+    }
+
+    /// objects are sharded by the first two hex digits of their hash
+    fn object_path(&self, md5: &str) -> PathBuf {
+        let shard = md5.get(..2).unwrap_or("00");
+        self.dir.join(OBJECTS_DIR).join(shard).join(format!("{}.pdf", md5))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.index.lock()
+            .expect("cache index lock poisoned")
+    }
+}
+
+/// hex MD5 of some bytes
+pub fn md5_hex(bytes: &[u8]) -> String {
+    Md5::digest(bytes).iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// converts the base64 MD5 that GCS reports for an object to hex
+pub fn gcs_md5_to_hex(md5_base64: &str) -> AppResult<String> {
+    let digest = base64::engine::general_purpose::STANDARD.decode(md5_base64)
+        .map_err(|err| AppError::Other(format!("invalid GCS md5 hash '{}': {}", md5_base64, err)))?;
+    Ok(digest.iter().map(|byte| format!("{:02x}", byte)).collect())
+}
+
+fn touch_object(index: &Connection, md5: &str) -> AppResult<()> {
+    index.execute(
+        "UPDATE cache_object SET last_used_ms = :now WHERE md5 = :md5",
+        named_params! { ":md5": md5, ":now": now_ms() }
+    )?;
+    Ok(())
+}
+
+fn upsert_entry(index: &Connection, entry: &CacheEntry) -> AppResult<()> {
+    index.execute(
+        "INSERT OR REPLACE INTO cache_entry (arxiv_id, version, md5, object_name, generation)
+         VALUES (:arxiv_id, :version, :md5, :object_name, :generation)",
+        named_params! {
+            ":arxiv_id": entry.arxiv_id,
+            ":version": entry.version,
+            ":md5": entry.md5,
+            ":object_name": entry.object_name,
+            ":generation": entry.generation,
+        }
+    )?;
+    Ok(())
+}
+
+fn is_stale(path: &Path) -> AppResult<bool> {
+    let modified = fs::metadata(path)?.modified()?;
+    let age = SystemTime::now().duration_since(modified).unwrap_or_default();
+    Ok(age > STALE_TEMP_AGE)
+}
+
+fn now_ms() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn entry(arxiv_id: &str, bytes: &[u8]) -> CacheEntry {
+        CacheEntry {
+            arxiv_id: arxiv_id.to_string(),
+            version: 1,
+            md5: md5_hex(bytes),
+            object_name: None,
+            generation: None,
+        }
+    }
+
+    /// last use is kept in milliseconds, so make sure consecutive uses don't share a timestamp
+    fn tick() {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b, c) = (b"%PDF a".as_slice(), b"%PDF b".as_slice(), b"%PDF c".as_slice());
+        let cache = PdfCache::open(dir.path()).unwrap()
+            .with_max_bytes(Some((a.len() + b.len()) as u64));
+
+        cache.put(&entry("2101.00001", a), a).unwrap();
+        tick();
+        cache.put(&entry("2101.00002", b), b).unwrap();
+        tick();
+        // using a makes b the least recently used
+        assert_eq!(cache.get(&entry("2101.00001", a)).unwrap().as_deref(), Some(a));
+        tick();
+        cache.put(&entry("2101.00003", c), c).unwrap();
+
+        assert!(cache.get(&entry("2101.00002", b)).unwrap().is_none());
+        assert!(cache.lookup("2101.00002", 1).unwrap().is_none());
+        assert!(!cache.object_path(&md5_hex(b)).exists());
+        assert_eq!(cache.get(&entry("2101.00001", a)).unwrap().as_deref(), Some(a));
+        assert_eq!(cache.get(&entry("2101.00003", c)).unwrap().as_deref(), Some(c));
+
+        let stats = cache.stats().unwrap();
+        assert_eq!((stats.objects, stats.total_bytes), (2, (a.len() + c.len()) as u64));
+    }
+
+    #[test]
+    fn refuses_bytes_that_dont_match() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PdfCache::open(dir.path()).unwrap();
+        let err = cache.put(&entry("2101.00001", b"%PDF a"), b"%PDF b").unwrap_err();
+        assert!(matches!(err, AppError::ChecksumMismatch { .. }), "{}", err);
+        assert_eq!(cache.stats().unwrap().objects, 0);
+    }
+
+    #[test]
+    fn verify_finds_corrupt_and_missing_objects() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PdfCache::open(dir.path()).unwrap();
+        let (good, corrupt, missing) = (b"%PDF good".as_slice(), b"%PDF corrupt".as_slice(), b"%PDF missing".as_slice());
+        for (arxiv_id, bytes) in [("2101.00001", good), ("2101.00002", corrupt), ("2101.00003", missing)] {
+            cache.put(&entry(arxiv_id, bytes), bytes).unwrap();
+        }
+        fs::write(cache.object_path(&md5_hex(corrupt)), b"%PDF truncat").unwrap();
+        fs::remove_file(cache.object_path(&md5_hex(missing))).unwrap();
+
+        let report = cache.verify(false).unwrap();
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.corrupt, vec![md5_hex(corrupt)]);
+        assert_eq!(report.missing, vec![md5_hex(missing)]);
+        assert!(!report.repaired);
+        // a plain verify changes nothing
+        assert_eq!(cache.stats().unwrap().objects, 3);
+
+        let report = cache.verify(true).unwrap();
+        assert!(report.repaired);
+        assert!(cache.lookup("2101.00002", 1).unwrap().is_none());
+        assert!(cache.lookup("2101.00003", 1).unwrap().is_none());
+        assert!(!cache.object_path(&md5_hex(corrupt)).exists());
+        assert_eq!(cache.verify(false).unwrap().checked, 1);
+    }
+
+    #[test]
+    fn prune_evicts_and_removes_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = PdfCache::open(dir.path()).unwrap();
+        let (a, b) = (b"%PDF a".as_slice(), b"%PDF b".as_slice());
+        cache.put(&entry("2101.00001", a), a).unwrap();
+        tick();
+        cache.put(&entry("2101.00002", b), b).unwrap();
+
+        // a file the index doesn't know, an abandoned partial write and one still being written
+        let orphan = cache.object_path(&md5_hex(b"%PDF orphan"));
+        fs::create_dir_all(orphan.parent().unwrap()).unwrap();
+        fs::write(&orphan, b"%PDF orphan").unwrap();
+        let abandoned = cache.object_path(&md5_hex(a)).with_extension("tmp-0000000000000001");
+        File::create(&abandoned).unwrap()
+            .set_modified(SystemTime::now() - STALE_TEMP_AGE * 2)
+            .unwrap();
+        let in_progress = cache.object_path(&md5_hex(a)).with_extension("tmp-0000000000000002");
+        File::create(&in_progress).unwrap();
+
+        let report = cache.prune(b.len() as u64).unwrap();
+        assert_eq!((report.evicted_objects, report.evicted_bytes), (1, a.len() as u64));
+        assert_eq!(report.removed_orphans, 2);
+        assert!(!orphan.exists());
+        assert!(!abandoned.exists());
+        assert!(in_progress.exists());
+        assert!(cache.lookup("2101.00001", 1).unwrap().is_none());
+        assert!(cache.lookup("2101.00002", 1).unwrap().is_some());
+    }
+}
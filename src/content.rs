@@ -1,4 +1,7 @@
 use rusqlite::Row;
+use rusqlite::types::Type;
+use crate::content::body::BodyBoundaries;
+use crate::content::keyword::KeywordStrategy;
 
 pub mod keyword;
 pub mod header;
@@ -15,6 +18,34 @@ pub struct ArxivPaperContent {
     pub keywords: Vec<String>,
     /// the actual content of the paper, intro through the end
     pub paper_content: String,
+    /// how the keywords and body were extracted. `None` for abstract-only rows from the metadata
+    pub provenance: Option<ExtractionProvenance>,
+}
+
+/// full text of a paper's PDF, kept so extraction can be re-run without downloading it again
+#[derive(Debug, Clone)]
+pub struct PaperText {
+    /// unversioned arxiv id
+    pub arxiv_id: String,
+    /// the paper version the PDF belongs to, if known
+    pub version: Option<u32>,
+    pub text: String,
+    /// bucket object the PDF was downloaded from
+    pub source_object: Option<String>,
+    pub source_generation: Option<i64>,
+}
+
+/// records which logic produced an extraction, so rows from outdated logic can be found and re-run
+#[derive(Debug, Clone)]
+pub struct ExtractionProvenance {
+    pub keyword_strategy: KeywordStrategy,
+    pub body_boundaries: BodyBoundaries,
+    pub extractor_version: &'static str,
+    /// ISO-8601 UTC time of the extraction
+    pub extracted_utc: String,
+    /// bucket object the text was extracted from, if it was downloaded
+    pub source_object: Option<String>,
+    pub source_generation: Option<i64>,
 }
 
 pub struct ArxivPaperContentEntity {
@@ -22,8 +53,8 @@ pub struct ArxivPaperContentEntity {
     pub id: String,
     /// abstract content
     pub abstract_text: String,
-    /// keywords
-    pub keywords: String,
+    /// keywords, in the order they appear in the paper
+    pub keywords: Vec<String>,
     /// the actual content of the paper, intro through the end
     pub paper_content: String,
 }
@@ -34,7 +65,10 @@ impl<'a, 'db> TryFrom<&'a Row<'db>> for ArxivPaperContentEntity {
     fn try_from(value: &'a Row<'db>) -> Result<Self, Self::Error> {
         let id = value.get::<_, String>("arxiv_id")?;
         let abstract_text = value.get::<_, String>("abstract")?;
-        let keywords = value.get::<_, String>("keywords")?;
+        // the training_data view aggregates keywords into a JSON array
+        let keywords_json = value.get::<_, String>("keywords")?;
+        let keywords = serde_json::from_str::<Vec<String>>(&keywords_json)
+            .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(err)))?;
         let paper_content = value.get::<_, String>("content")?;
         
         Ok(Self {
@@ -2,12 +2,25 @@ use regex::Regex;
 use crate::content::regexes::intro_header_regex_factory;
 use crate::err::{AppError, AppResult};
 
+/// the regexes that delimited an extracted body
+#[derive(Debug, Clone)]
+pub struct BodyBoundaries {
+    pub start_pattern: String,
+    pub end_pattern: String,
+}
+
 pub struct PaperBodyExtractor {
     intro_header: Regex,
     references_header: Regex,
     references_header_permissive: Regex,
 }
 
+impl Default for PaperBodyExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PaperBodyExtractor {
     pub fn new() -> Self {
         Self {
@@ -18,6 +31,12 @@ impl PaperBodyExtractor {
     }
     
     pub fn extract_body(&self, content: &str) -> AppResult<String> {
+        self.extract_body_with_boundaries(content)
+            .map(|(body, _)| body)
+    }
+
+    /// extracts the body along with the patterns that marked its start and end
+    pub fn extract_body_with_boundaries(&self, content: &str) -> AppResult<(String, BodyBoundaries)> {
         // find where the intro starts
         let intro_match = self.intro_header.find(content)
             .ok_or(AppError::MissingSection("INTRODUCTION".to_string()))?;
@@ -25,13 +44,21 @@ impl PaperBodyExtractor {
         // get the starting bound
         let content_start = intro_match.start();
         
-        let references_match = self.references_header.find_at(content, content_start)
-            .or_else(|| self.references_header_permissive.find_at(content, content_start))
+        let (references_match, references_header) = self.references_header.find_at(content, content_start)
+            .map(|found| (found, &self.references_header))
+            .or_else(|| {
+                self.references_header_permissive.find_at(content, content_start)
+                    .map(|found| (found, &self.references_header_permissive))
+            })
             .ok_or(AppError::MissingSection("REFERENCES".to_string()))?;
         
         let content_end = references_match.start();
         
-        let content = String::from((&content[content_start..content_end]).trim());
-        Ok(content)
+        let content = String::from(content[content_start..content_end].trim());
+        let boundaries = BodyBoundaries {
+            start_pattern: self.intro_header.as_str().to_string(),
+            end_pattern: references_header.as_str().to_string(),
+        };
+        Ok((content, boundaries))
     }
 }
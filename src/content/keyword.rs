@@ -1,8 +1,54 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use log::debug;
 use crate::err::{AppError, AppResult};
 use regex::{Regex};
 use crate::content::regexes::intro_header_regex_factory;
 
+/// the approach that found a paper's keywords, recorded so rows from outdated logic can be re-run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeywordStrategy {
+    /// a standalone `Keywords` heading, up to the introduction
+    Headers,
+    /// an inline `Keywords:` label, up to the next blank line
+    SmallLabel,
+    /// `Index Terms` up to the first period
+    IndexTermsPeriod,
+    /// an `Index Terms` heading, up to the introduction
+    IndexTermsHeaders,
+}
+
+impl KeywordStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeywordStrategy::Headers => "headers",
+            KeywordStrategy::SmallLabel => "small_label",
+            KeywordStrategy::IndexTermsPeriod => "index_terms_period",
+            KeywordStrategy::IndexTermsHeaders => "index_terms_headers",
+        }
+    }
+}
+
+impl Display for KeywordStrategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for KeywordStrategy {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "headers" => Ok(KeywordStrategy::Headers),
+            "small_label" => Ok(KeywordStrategy::SmallLabel),
+            "index_terms_period" => Ok(KeywordStrategy::IndexTermsPeriod),
+            "index_terms_headers" => Ok(KeywordStrategy::IndexTermsHeaders),
+            _ => Err(AppError::Other(format!("unknown keyword strategy '{}'", s))),
+        }
+    }
+}
+
 pub struct KeywordExtractor {
     keywords_header: Regex,
     intro_header: Regex,
@@ -11,6 +57,12 @@ pub struct KeywordExtractor {
     index_terms_dot: Regex,
 }
 
+impl Default for KeywordExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl KeywordExtractor {
     pub fn new() -> Self {
         Self {
@@ -23,20 +75,28 @@ impl KeywordExtractor {
     }
 
     pub fn extract_keywords(&self, contents: &str) -> AppResult<Vec<String>> {
+        self.extract_keywords_with_strategy(contents)
+            .map(|(_, keywords)| keywords)
+    }
+
+    /// extracts keywords along with the strategy that found them
+    pub fn extract_keywords_with_strategy(&self, contents: &str) -> AppResult<(KeywordStrategy, Vec<String>)> {
+        let tagged = |strategy| move |keywords| (strategy, keywords);
+
         // try each different approach
-        self.extract_keywords_headers(contents)
-            .or_else(|_| self.extract_keywords_small_label(contents))
-            .or_else(|_| self.extract_keywords_index_terms_period(contents))
-            .or_else(|_| self.extract_keywords_headers_index_terms(contents))
+        self.extract_keywords_headers(contents).map(tagged(KeywordStrategy::Headers))
+            .or_else(|_| self.extract_keywords_small_label(contents).map(tagged(KeywordStrategy::SmallLabel)))
+            .or_else(|_| self.extract_keywords_index_terms_period(contents).map(tagged(KeywordStrategy::IndexTermsPeriod)))
+            .or_else(|_| self.extract_keywords_headers_index_terms(contents).map(tagged(KeywordStrategy::IndexTermsHeaders)))
     }
 
     pub fn extract_keywords_headers(&self, contents: &str) -> AppResult<Vec<String>> {
         debug!("using keyword extraction headers strategy");
-        let keywords_section = self.keywords_header.find(&contents).ok_or(AppError::NoKeywords)?;
+        let keywords_section = self.keywords_header.find(contents).ok_or(AppError::NoKeywords)?;
         let keywords_section_end = keywords_section.end();
         let refined_area = &contents[keywords_section_end..];
 
-        let intro_match = self.intro_header.find(&refined_area).ok_or(AppError::NoKeywords)?;
+        let intro_match = self.intro_header.find(refined_area).ok_or(AppError::NoKeywords)?;
         let intro_start = intro_match.start();
 
         let interesting_section = &refined_area[..intro_start];
@@ -57,11 +117,11 @@ impl KeywordExtractor {
     
     pub fn extract_keywords_headers_index_terms(&self, contents: &str) -> AppResult<Vec<String>> {
         debug!("using keyword extraction headers (key terms) strategy");
-        let keywords_section = self.index_terms.find(&contents).ok_or(AppError::NoKeywords)?;
+        let keywords_section = self.index_terms.find(contents).ok_or(AppError::NoKeywords)?;
         let keywords_section_end = keywords_section.end();
         let refined_area = &contents[keywords_section_end..];
 
-        let intro_match = self.intro_header.find(&refined_area).ok_or(AppError::NoKeywords)?;
+        let intro_match = self.intro_header.find(refined_area).ok_or(AppError::NoKeywords)?;
         let intro_start = intro_match.start();
 
         let interesting_section = &refined_area[..intro_start];
@@ -73,7 +133,7 @@ impl KeywordExtractor {
     pub fn extract_keywords_small_label(&self, contents: &str) -> AppResult<Vec<String>> {
         debug!("using small headers strategy");
         // find the small extractor header
-        let result = self.small_extractor.find(&contents)
+        let result = self.small_extractor.find(contents)
             .ok_or(AppError::NoKeywords)?;
 
         let search_space = &contents[result.end()..];
@@ -92,6 +152,15 @@ impl KeywordExtractor {
     } 
 }
 
+/// the form keywords are compared and grouped by: lowercase, with runs of whitespace (including
+/// line breaks from the PDF layout) collapsed to single spaces
+pub fn normalize_keyword(keyword: &str) -> String {
+    keyword.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
 fn take_until_double_newline(content: &str) -> String {
     let mut output = String::new();
     let mut last_was_nl = false;
@@ -1,46 +1,220 @@
+pub mod check;
+pub mod migration;
 pub mod pages;
+pub mod stats;
 
-use crate::err::{AppResult};
-use crate::metadata::{ArxivMetadata, ArxivVersion};
-use rusqlite::{named_params, Connection, Statement, Transaction};
+use crate::err::{AppError, AppResult};
+use crate::metadata::{ArxivAuthor, ArxivMetadata, ArxivVersion};
+use crate::metadata::filter::{DateRange, VersionSelector};
+use crate::metadata::id::ArxivId;
+use crate::metadata::to_iso8601_utc;
+use rusqlite::{named_params, Connection, OptionalExtension, Row, Statement, ToSql, Transaction};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, Value, ValueRef};
+use serde::Serialize;
+use chrono::Utc;
 use std::path::Path;
-use crate::content::{ArxivPaperContent, ArxivPaperContentEntity};
+use std::time::Duration;
+use log::warn;
+use crate::content::{ArxivPaperContent, ArxivPaperContentEntity, PaperText};
+use crate::content::keyword::{normalize_keyword, KeywordStrategy};
+use crate::db::migration::{run_migrations, Migration, SchemaStatus, LATEST_SCHEMA_VERSION};
 use crate::db::pages::QueryPage;
 use crate::extraction::{ExtractError, ExtractResultRecord};
 
+/// SQLite journal mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalMode {
+    /// write-ahead log: readers don't block the writer and the writer doesn't block readers
+    Wal,
+    /// rollback journal, deleted after each transaction
+    Delete,
+    /// rollback journal, truncated after each transaction
+    Truncate,
+}
+
+impl JournalMode {
+    fn pragma_value(&self) -> &'static str {
+        match self {
+            JournalMode::Wal => "WAL",
+            JournalMode::Delete => "DELETE",
+            JournalMode::Truncate => "TRUNCATE",
+        }
+    }
+}
+
+/// how hard SQLite works to keep committed data through a crash or power loss, i.e. `synchronous`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Durability {
+    /// never syncs. Fastest, but a power loss can corrupt the database.
+    Off,
+    /// in WAL mode, a power loss may drop the last commits but never corrupts the database
+    Normal,
+    /// syncs on every commit
+    Full,
+}
+
+impl Durability {
+    fn pragma_value(&self) -> &'static str {
+        match self {
+            Durability::Off => "OFF",
+            Durability::Normal => "NORMAL",
+            Durability::Full => "FULL",
+        }
+    }
+}
+
+/// settings applied to every connection when it is opened
+#[derive(Debug, Clone)]
+pub struct ConnectionOptions {
+    journal_mode: JournalMode,
+    durability: Durability,
+    /// how long to wait on a lock held by another connection before giving up
+    busy_timeout: Duration,
+}
+
+impl Default for ConnectionOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConnectionOptions {
+    pub fn new() -> Self {
+        Self {
+            journal_mode: JournalMode::Wal,
+            durability: Durability::Normal,
+            busy_timeout: Duration::from_secs(5),
+        }
+    }
+
+    pub fn with_journal_mode(mut self, journal_mode: JournalMode) -> Self {
+        self.journal_mode = journal_mode;
+        self
+    }
+
+    pub fn with_durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    pub fn with_busy_timeout(mut self, busy_timeout: Duration) -> Self {
+        self.busy_timeout = busy_timeout;
+        self
+    }
+}
+
 pub struct ArxivDB {
     conn: Connection,
 } 
 
 impl ArxivDB {
+    /// opens the database with the default [ConnectionOptions]
     pub fn open<PathT: AsRef<Path>>(path: PathT) -> AppResult<Self> {
+        Self::open_with_options(path, &ConnectionOptions::default())
+    }
+
+    pub fn open_with_options<PathT: AsRef<Path>>(path: PathT, options: &ConnectionOptions) -> AppResult<Self> {
         let conn = Connection::open(path)?;
+        conn.busy_timeout(options.busy_timeout)?;
+        let journal_mode = conn.pragma_update_and_check(None, "journal_mode", options.journal_mode.pragma_value(), |row| row.get::<_, String>(0))?;
+        if !journal_mode.eq_ignore_ascii_case(options.journal_mode.pragma_value()) {
+            warn!("requested journal mode {} but database is using {}", options.journal_mode.pragma_value(), journal_mode);
+        }
+        conn.pragma_update(None, "synchronous", options.durability.pragma_value())?;
+        // off by default in stock SQLite builds; the schema relies on it to keep rows attached to papers
+        conn.pragma_update(None, "foreign_keys", true)?;
+
+        let status = SchemaStatus::read(&conn)?;
+        if status.is_newer_than_supported() {
+            return Err(AppError::SchemaTooNew {
+                found: status.user_version,
+                supported: LATEST_SCHEMA_VERSION,
+            });
+        }
+
         Ok(Self {
             conn
         })
     }
     
-    pub fn execute_ddl(&self) -> AppResult<()> {
-        let ddl_query = include_str!("../sql/ddl.sql");
-        self.conn.execute_batch(ddl_query)?;
-        Ok(())
+    /// brings the schema up to date, returning the migrations that were applied
+    pub fn migrate(&mut self) -> AppResult<Vec<&'static Migration>> {
+        self.migrate_to(LATEST_SCHEMA_VERSION)
     }
-    
-    pub fn turn_off_synchronous(&self) -> AppResult<()> {
-        self.conn.pragma_update(None, "synchronous", "OFF")?;
+
+    /// applies pending migrations up to `version` only, returning the ones that were applied
+    pub fn migrate_to(&mut self, version: u32) -> AppResult<Vec<&'static Migration>> {
+        run_migrations(&mut self.conn, version)
+    }
+
+    pub fn schema_status(&self) -> AppResult<SchemaStatus> {
+        SchemaStatus::read(&self.conn)
+    }
+
+    /// for readers, which shouldn't change the schema under a process that is writing to the
+    /// database. Fails unless the schema is up to date.
+    pub fn require_current_schema(&self) -> AppResult<()> {
+        self.require_schema(LATEST_SCHEMA_VERSION)
+    }
+
+    /// like [Self::require_current_schema], for readers that only need the schema up to `version`
+    pub fn require_schema(&self, version: u32) -> AppResult<()> {
+        let status = self.schema_status()?;
+        if !status.is_at_least(version) {
+            return Err(AppError::SchemaOutdated {
+                found: status.version(),
+                expected: version,
+            });
+        }
+
         Ok(())
     }
-    
-    pub fn queries(&self) -> ArxivDBQueries {
+
+    pub fn queries(&self) -> ArxivDBQueries<'_> {
         ArxivDBQueries::wrap(&self.conn)
     }
     
-    pub fn txn(&mut self) -> AppResult<Transaction> {
+    pub fn txn(&mut self) -> AppResult<Transaction<'_>> {
         self.conn.transaction()
             .map_err(Into::into)
     }
 }
 
+/// how stored paper text is compressed
+const TEXT_COMPRESSION: &str = "zstd";
+const TEXT_COMPRESSION_LEVEL: i32 = 9;
+
+/// a paper matching a full-text search
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub arxiv_id: String,
+    pub title: Option<String>,
+    /// best matching fragment, with matched terms wrapped in `**`
+    pub snippet: String,
+    /// BM25 score; lower is a better match
+    pub rank: f64,
+}
+
+/// number of papers filed under a category
+#[derive(Debug, Clone)]
+pub struct CategoryCount {
+    pub code: String,
+    /// human-readable name, if the code is in the category reference table
+    pub name: Option<String>,
+    pub paper_count: u64,
+}
+
+/// what happened to a metadata record during an incremental load
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    /// the paper was not in the database yet
+    Inserted,
+    /// metadata fields changed or new versions were appended
+    Updated,
+    /// the record matched what was already stored
+    Unchanged,
+}
+
 pub struct ArxivDBQueries<'a> {
     conn: &'a Connection,
 }
@@ -53,6 +227,19 @@ impl<'a> ArxivDBQueries<'a> {
         }
     }
 
+    /// runs `body` in its own transaction, committing if it succeeds. Must not be called while
+    /// another transaction is open on the connection.
+    pub fn transaction<T, BodyT>(&self, body: BodyT) -> AppResult<T>
+    where
+        BodyT: FnOnce(&Self) -> AppResult<T>,
+    {
+        // queries made through `self` run on the same connection, so they land in the transaction
+        let txn = self.conn.unchecked_transaction()?;
+        let result = body(self)?;
+        txn.commit()?;
+        Ok(result)
+    }
+
     pub fn count_arxiv_ids(&self) -> AppResult<u64> {
         let result = self.conn.query_row(
             "SELECT COUNT(id) FROM arxiv_metadata",
@@ -63,50 +250,79 @@ impl<'a> ArxivDBQueries<'a> {
         Ok(result)
     }
 
-    pub fn count_training_data(&self) -> AppResult<u64> {
+    /// counts training records, optionally only those matching a full-text query
+    pub fn count_training_data(&self, search: Option<&str>) -> AppResult<u64> {
+        let query = format!("SELECT COUNT(arxiv_id) FROM training_data WHERE {}", search_condition(search));
         self.conn.query_row(
-            r"SELECT COUNT(arxiv_id) FROM training_data",
-            [],
+            &query,
+            named_params! { ":search": search },
             |row| row.get::<_, u64>(0)
         )
             .map_err(|err| err.into())
     }
     
-    pub fn select_training_data(&self, page: QueryPage) -> AppResult<Vec<ArxivPaperContentEntity>> {
-        let mut stmt = self.conn.prepare_cached(r"
+    /// one keyset page of training data, ordered by arxiv id, optionally only records matching a
+    /// full-text query
+    pub fn select_training_data(&self, page: &QueryPage, search: Option<&str>) -> AppResult<Vec<ArxivPaperContentEntity>> {
+        let query = format!(r"
         SELECT * FROM training_data
+        WHERE arxiv_id > :after AND {}
+        ORDER BY arxiv_id
         LIMIT :limit
-        OFFSET :offset
-        ")?;
+        ", search_condition(search));
+        let mut stmt = self.conn.prepare_cached(&query)?;
         
         let params = named_params! {
-            ":offset": page.offset,
-            ":limit": page.limit
+            ":after": page.after,
+            ":limit": page.limit,
+            ":search": search,
         };
         
         let entities = stmt.query_map(params, |row| ArxivPaperContentEntity::try_from(row))?
-            .filter_map(|entity| entity.ok())
-            .collect::<Vec<_>>();
+            .collect::<Result<Vec<_>, _>>()?;
         
         Ok(entities)
     }
+
+    /// full-text search over titles, abstracts, bodies and keywords, best matches first. `query`
+    /// uses FTS5 syntax, e.g. `keywords: "federated learning"` or `contrastive NEAR loss`.
+    pub fn search_papers(&self, query: &str, limit: u64) -> AppResult<Vec<SearchHit>> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT arxiv_id, title, snippet(paper_search, -1, '**', '**', '...', 16), rank
+        FROM paper_search
+        WHERE paper_search MATCH :query
+        ORDER BY rank
+        LIMIT :limit
+        ")?;
+
+        let hits = stmt.query_map(named_params! { ":query": query, ":limit": limit }, |row| {
+            Ok(SearchHit {
+                arxiv_id: row.get(0)?,
+                title: row.get(1)?,
+                snippet: row.get(2)?,
+                rank: row.get(3)?,
+            })
+        })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(hits)
+    }
     
-    pub fn select_arxiv_ids(&self, page: QueryPage) -> AppResult<Vec<String>> {
-        let mut stmt = self.conn.prepare("SELECT id FROM arxiv_metadata LIMIT :limit OFFSET :offset")?;
+    /// one keyset page of arxiv ids, in id order
+    pub fn select_arxiv_ids(&self, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        let mut stmt = self.conn.prepare_cached("SELECT id FROM arxiv_metadata WHERE id > :after ORDER BY id LIMIT :limit")?;
         let params = named_params! {
+            ":after": page.after,
             ":limit": page.limit,
-            ":offset": page.offset,
         };
         
         let ids = stmt
-            .query_map(params, |row| row.get::<_, String>("id"))?
-            .filter_map(|id| id.ok())
-            .collect::<Vec<_>>();
+            .query_map(params, |row| row.get::<_, ArxivId>("id"))?
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(ids)
     }
     
-    pub fn sample_arxiv_ids(&self, count: u64) -> AppResult<Vec<String>> {
+    pub fn sample_arxiv_ids(&self, count: u64) -> AppResult<Vec<ArxivId>> {
         let stmt = self.conn.prepare(r"
         SELECT id FROM arxiv_metadata ORDER BY random() LIMIT :limit
         ")?;
@@ -114,7 +330,7 @@ impl<'a> ArxivDBQueries<'a> {
         Self::map_id_query(stmt, count)
     }
     
-    pub fn sample_arxiv_ids_unprocessed(&self, count: u64) -> AppResult<Vec<String>> {
+    pub fn sample_arxiv_ids_unprocessed(&self, count: u64) -> AppResult<Vec<ArxivId>> {
         let stmt = self.conn.prepare(r"
         WITH candidates AS (
             SELECT id FROM arxiv_metadata WHERE NOT EXISTS (
@@ -127,67 +343,610 @@ impl<'a> ArxivDBQueries<'a> {
         Self::map_id_query(stmt, count)
     }
     
-    fn map_id_query(mut stmt: Statement, count: u64) -> AppResult<Vec<String>> {
+    fn map_id_query(mut stmt: Statement, count: u64) -> AppResult<Vec<ArxivId>> {
         let params = named_params! { ":limit": count };
 
-        let ids = stmt.query_map(params, |row| row.get::<_, String>("id"))?
-            .filter_map(|id| id.ok())
-            .collect::<Vec<_>>();
+        let ids = stmt.query_map(params, |row| row.get::<_, ArxivId>("id"))?
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(ids)
     }
     
-    pub fn insert_extraction_result(&self, id: &str, err: Option<ExtractError>) -> AppResult<()> {
-        let record = err
-            .map(ExtractResultRecord::from)
-            .unwrap_or(ExtractResultRecord::success(id));
-        
+    /// records an extraction attempt as the paper's next attempt
+    pub fn insert_extraction_result(&self, id: &str, err: Option<ExtractError>, duration: Duration) -> AppResult<()> {
+        let record = ExtractResultRecord::attempt(id, err, duration);
+
         let mut stmt = self.conn.prepare_cached(r"
-        INSERT INTO extraction_result (arxiv_id, status_code, status_msg)
-        VALUES (:arxiv_id, :status_code, :status_msg)
+        INSERT INTO extraction_attempt (arxiv_id, attempt, status_code, status_msg, attempted_utc, duration_ms)
+        VALUES (
+            :arxiv_id,
+            COALESCE((SELECT MAX(attempt) FROM extraction_attempt WHERE arxiv_id = :arxiv_id), 0) + 1,
+            :status_code,
+            :status_msg,
+            :attempted_utc,
+            :duration_ms
+        )
         ")?;
-        
+
         let params = named_params! {
             ":arxiv_id": record.arxiv_id,
             ":status_code": record.extract_status,
-            ":status_msg": record.extract_msg
+            ":status_msg": record.extract_msg,
+            ":attempted_utc": to_iso8601_utc(&Utc::now()),
+            ":duration_ms": record.duration.map(|duration| duration.as_millis() as u64),
         };
-        
+
         stmt.execute(params)?;
 
         Ok(())
     }
+
+    /// counts papers whose latest extraction isn't `OK`, including never attempted ones, leaving
+    /// out those already attempted `max_attempts` times
+    pub fn count_pending_arxiv_ids(&self, max_attempts: Option<u32>) -> AppResult<u64> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT COUNT(*) FROM arxiv_metadata
+        LEFT JOIN extraction_result ON extraction_result.arxiv_id = arxiv_metadata.id
+        WHERE extraction_result.status_code IS NOT 'OK'
+            AND (:max_attempts IS NULL OR COALESCE(extraction_result.attempts, 0) < :max_attempts)
+        ")?;
+
+        Ok(stmt.query_row(named_params! { ":max_attempts": max_attempts }, |row| row.get::<_, u64>(0))?)
+    }
+
+    /// one keyset page of the papers [Self::count_pending_arxiv_ids] counts
+    pub fn select_pending_arxiv_ids(&self, max_attempts: Option<u32>, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT arxiv_metadata.id FROM arxiv_metadata
+        LEFT JOIN extraction_result ON extraction_result.arxiv_id = arxiv_metadata.id
+        WHERE arxiv_metadata.id > :after
+            AND extraction_result.status_code IS NOT 'OK'
+            AND (:max_attempts IS NULL OR COALESCE(extraction_result.attempts, 0) < :max_attempts)
+        ORDER BY arxiv_metadata.id
+        LIMIT :limit
+        ")?;
+        let params = named_params! {
+            ":after": page.after,
+            ":limit": page.limit,
+            ":max_attempts": max_attempts,
+        };
+
+        let ids = stmt.query_map(params, |row| row.get::<_, ArxivId>("id"))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+
+    /// counts papers whose latest extraction status is one of `statuses`, leaving out those
+    /// already attempted `max_attempts` times
+    pub fn count_retry_candidates(&self, statuses: &[String], max_attempts: Option<u32>) -> AppResult<u64> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT COUNT(*) FROM extraction_result
+        WHERE status_code IN (SELECT value FROM json_each(:statuses))
+            AND (:max_attempts IS NULL OR attempts < :max_attempts)
+            AND EXISTS (SELECT 1 FROM arxiv_metadata WHERE arxiv_metadata.id = extraction_result.arxiv_id)
+        ")?;
+        let params = named_params! {
+            ":statuses": serde_json::to_string(statuses)?,
+            ":max_attempts": max_attempts,
+        };
+
+        Ok(stmt.query_row(params, |row| row.get::<_, u64>(0))?)
+    }
+
+    /// one keyset page of the papers [Self::count_retry_candidates] counts
+    pub fn select_retry_candidates(&self, statuses: &[String], max_attempts: Option<u32>, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT arxiv_id AS id FROM extraction_result
+        WHERE arxiv_id > :after
+            AND status_code IN (SELECT value FROM json_each(:statuses))
+            AND (:max_attempts IS NULL OR attempts < :max_attempts)
+            AND EXISTS (SELECT 1 FROM arxiv_metadata WHERE arxiv_metadata.id = extraction_result.arxiv_id)
+        ORDER BY arxiv_id
+        LIMIT :limit
+        ")?;
+        let params = named_params! {
+            ":after": page.after,
+            ":limit": page.limit,
+            ":statuses": serde_json::to_string(statuses)?,
+            ":max_attempts": max_attempts,
+        };
+
+        let ids = stmt.query_map(params, |row| row.get::<_, ArxivId>("id"))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
     
     pub fn insert_arxiv_metadata(&self, metadata: ArxivMetadata) -> AppResult<()> {
-        let arxiv_id = metadata.id().expect("metadata has null arxiv id");
+        let arxiv_id = &metadata.arxiv_id()?.base_id();
         self.insert_metadata(&metadata)?;
         self.insert_versions(arxiv_id, metadata.versions())?;
+        self.insert_paper_authors(arxiv_id, metadata.authors_parsed())?;
+        self.insert_paper_categories(arxiv_id, &metadata.category_list())?;
 
         // make a quick content for the abstract
         self.insert_content(arxiv_id, ArxivPaperContent {
             id: "".to_string(),
             abstract_text: metadata.abstract_text().cloned().unwrap_or_default(),
             paper_content: String::default(),
-            keywords: Vec::default()
+            keywords: Vec::default(),
+            provenance: None,
         })?;
 
         Ok(())
     }
 
+    /// inserts a batch of new papers using multi-row inserts. Equivalent to calling
+    /// [Self::insert_arxiv_metadata] for each record, but much faster for large loads.
+    pub fn insert_arxiv_metadata_batch(&self, batch: &[ArxivMetadata]) -> AppResult<()> {
+        let mut metadata_rows = Vec::with_capacity(batch.len() * 9);
+        let mut version_rows = Vec::new();
+        let mut category_rows = Vec::new();
+        let mut content_rows = Vec::with_capacity(batch.len() * 3);
+        let mut ids = Vec::with_capacity(batch.len());
+
+        for metadata in batch {
+            let arxiv_id = metadata.arxiv_id()?.base_id();
+            metadata_rows.extend([
+                Value::from(arxiv_id.clone()),
+                optional_text(metadata.submitter()),
+                optional_text(metadata.authors()),
+                optional_text(metadata.title()),
+                optional_text(metadata.comments()),
+                optional_text(metadata.journal_ref()),
+                optional_text(metadata.doi()),
+                optional_text(metadata.categories()),
+                optional_text(metadata.update_date()),
+            ]);
+
+            for version in metadata.versions() {
+                version_rows.extend([
+                    Value::from(arxiv_id.clone()),
+                    Value::from(version.version().to_string()),
+                    Value::from(version.created().to_string()),
+                    version.created_utc().map(Value::from).unwrap_or(Value::Null),
+                ]);
+            }
+
+            for (position, category) in metadata.category_list().into_iter().enumerate() {
+                category_rows.extend([
+                    Value::from(arxiv_id.clone()),
+                    Value::from(category.to_string()),
+                    Value::from(position as i64),
+                    Value::from(position == 0),
+                ]);
+            }
+
+            // make a quick content for the abstract
+            content_rows.extend([
+                Value::from(arxiv_id.clone()),
+                Value::from(metadata.abstract_text().cloned().unwrap_or_default()),
+                Value::from(String::new()),
+            ]);
+
+            ids.push(arxiv_id);
+        }
+
+        self.insert_rows(
+            "INSERT INTO arxiv_metadata (id, submitted, authors, title, comments, journal_ref, doi, categories, update_date)",
+            9,
+            metadata_rows,
+        )?;
+        self.insert_rows("INSERT INTO arxiv_version (arxiv_id, version, created, created_utc)", 4, version_rows)?;
+        self.insert_rows("INSERT OR IGNORE INTO paper_category (arxiv_id, category, position, is_primary)", 4, category_rows)?;
+        self.insert_rows("INSERT INTO paper_data (arxiv_id, abstract, content)", 3, content_rows)?;
+
+        // authors need their generated ids, so they go row by row
+        for (arxiv_id, metadata) in ids.iter().zip(batch) {
+            self.insert_paper_authors(arxiv_id, metadata.authors_parsed())?;
+        }
+
+        Ok(())
+    }
+
+    /// runs `insert_prefix VALUES (...), (...)` over the flattened row values, in chunks that stay
+    /// under SQLite's bound parameter limit
+    fn insert_rows(&self, insert_prefix: &str, width: usize, values: Vec<Value>) -> AppResult<()> {
+        const MAX_ROWS_PER_STATEMENT: usize = 500;
+        let row_placeholder = format!("({})", vec!["?"; width].join(", "));
+
+        for rows in values.chunks(width * MAX_ROWS_PER_STATEMENT) {
+            let row_count = rows.len() / width;
+            let query = format!("{} VALUES {}", insert_prefix, vec![row_placeholder.as_str(); row_count].join(", "));
+            let mut stmt = self.conn.prepare_cached(&query)?;
+            stmt.execute(rusqlite::params_from_iter(rows))?;
+        }
+
+        Ok(())
+    }
+
+    /// inserts a new paper, or brings an existing one up to date with a newer metadata snapshot.
+    /// Changed metadata fields and the abstract are updated and unseen versions are appended.
+    /// Extracted content, keywords and extraction results are left untouched.
+    pub fn upsert_arxiv_metadata(&self, metadata: ArxivMetadata) -> AppResult<UpsertOutcome> {
+        let arxiv_id = &metadata.arxiv_id()?.base_id();
+        if !self.arxiv_id_exists(arxiv_id)? {
+            self.insert_arxiv_metadata(metadata)?;
+            return Ok(UpsertOutcome::Inserted);
+        }
+
+        let mut changed_rows = self.update_metadata(&metadata)?;
+        changed_rows += self.upsert_abstract(arxiv_id, metadata.abstract_text().map(String::as_str).unwrap_or_default())?;
+        changed_rows += self.insert_new_versions(arxiv_id, metadata.versions())?;
+        changed_rows += self.sync_paper_authors(arxiv_id, metadata.authors_parsed())?;
+        changed_rows += self.sync_paper_categories(arxiv_id, &metadata.category_list())?;
+
+        if changed_rows > 0 {
+            Ok(UpsertOutcome::Updated)
+        } else {
+            Ok(UpsertOutcome::Unchanged)
+        }
+    }
+
+    pub fn arxiv_id_exists(&self, arxiv_id: &str) -> AppResult<bool> {
+        let mut stmt = self.conn.prepare_cached("SELECT EXISTS(SELECT 1 FROM arxiv_metadata WHERE id = :id)")?;
+        let exists = stmt.query_row(named_params! { ":id": arxiv_id }, |row| row.get::<_, bool>(0))?;
+        Ok(exists)
+    }
+
+    pub fn paper_data_exists(&self, arxiv_id: &str) -> AppResult<bool> {
+        let mut stmt = self.conn.prepare_cached("SELECT EXISTS(SELECT 1 FROM paper_data WHERE arxiv_id = :id)")?;
+        let exists = stmt.query_row(named_params! { ":id": arxiv_id }, |row| row.get::<_, bool>(0))?;
+        Ok(exists)
+    }
+
+    /// updates the metadata row only if some field differs. Returns the number of changed rows.
+    pub fn update_metadata(&self, metadata: &ArxivMetadata) -> AppResult<usize> {
+        let mut stmt = self.conn.prepare_cached(r"
+        UPDATE arxiv_metadata
+        SET submitted = :submitted, authors = :authors, title = :title, comments = :comments,
+            journal_ref = :journal_ref, doi = :doi, categories = :categories, update_date = :update_date
+        WHERE id = :id AND (
+            submitted IS NOT :submitted OR authors IS NOT :authors OR title IS NOT :title OR
+            comments IS NOT :comments OR journal_ref IS NOT :journal_ref OR doi IS NOT :doi OR
+            categories IS NOT :categories OR update_date IS NOT :update_date
+        )
+        ")?;
+        let params = named_params! {
+            ":id": metadata.arxiv_id()?.base_id(),
+            ":submitted": metadata.submitter(),
+            ":authors": metadata.authors(),
+            ":title": metadata.title(),
+            ":comments": metadata.comments(),
+            ":journal_ref": metadata.journal_ref(),
+            ":doi": metadata.doi(),
+            ":categories": metadata.categories(),
+            ":update_date": metadata.update_date()
+        };
+
+        Ok(stmt.execute(params)?)
+    }
+
+    /// updates the stored abstract without touching extracted content, adding the paper_data row
+    /// if it is missing. Returns the number of changed rows.
+    pub fn upsert_abstract(&self, arxiv_id: &str, abstract_text: &str) -> AppResult<usize> {
+        let mut stmt = self.conn.prepare_cached(r"
+        INSERT INTO paper_data (arxiv_id, abstract) VALUES (:arxiv_id, :abstract)
+        ON CONFLICT (arxiv_id) DO UPDATE SET abstract = excluded.abstract
+        WHERE abstract IS NOT excluded.abstract
+        ")?;
+        let params = named_params! {
+            ":arxiv_id": arxiv_id,
+            ":abstract": abstract_text,
+        };
+
+        Ok(stmt.execute(params)?)
+    }
+
+    /// inserts only the versions that are not stored yet. Returns how many were added.
+    pub fn insert_new_versions(&self, arxiv_id: &str, versions: &[ArxivVersion]) -> AppResult<usize> {
+        let mut stmt = self.conn.prepare_cached(r"
+        INSERT INTO arxiv_version(arxiv_id, version, created, created_utc)
+        SELECT :arxiv_id, :version, :created, :created_utc
+        WHERE NOT EXISTS (
+            SELECT 1 FROM arxiv_version WHERE arxiv_id = :arxiv_id AND version = :version
+        )
+        ")?;
+
+        let mut inserted = 0;
+        for item in versions {
+            let params = named_params! {
+                ":arxiv_id": arxiv_id,
+                ":version": item.version(),
+                ":created": item.created(),
+                ":created_utc": item.created_utc()
+            };
+
+            inserted += stmt.execute(params)?;
+        }
+
+        Ok(inserted)
+    }
+
+    /// links the paper to its authors in order, creating author rows as needed. Authors are
+    /// identified by their exact (last name, first names, suffix) triple.
+    pub fn insert_paper_authors(&self, arxiv_id: &str, authors: &[ArxivAuthor]) -> AppResult<()> {
+        let mut author_stmt = self.conn.prepare_cached(r"
+        INSERT INTO author (last_name, first_names, suffix)
+        VALUES (:last_name, :first_names, :suffix)
+        ON CONFLICT (last_name, first_names, suffix) DO UPDATE SET last_name = excluded.last_name
+        RETURNING id
+        ")?;
+        let mut link_stmt = self.conn.prepare_cached(r"
+        INSERT INTO paper_author (arxiv_id, author_id, position)
+        VALUES (:arxiv_id, :author_id, :position)
+        ")?;
+
+        for (position, author) in authors.iter().enumerate() {
+            let author_params = named_params! {
+                ":last_name": author.last_name,
+                ":first_names": author.first_names,
+                ":suffix": author.suffix,
+            };
+            let author_id = author_stmt.query_row(author_params, |row| row.get::<_, i64>(0))?;
+
+            let link_params = named_params! {
+                ":arxiv_id": arxiv_id,
+                ":author_id": author_id,
+                ":position": position as i64,
+            };
+            link_stmt.execute(link_params)?;
+        }
+
+        Ok(())
+    }
+
+    /// replaces the paper's author links if they differ from the given list. Returns the number of
+    /// changed links. An empty list leaves existing links alone, since not every source carries
+    /// structured authors.
+    pub fn sync_paper_authors(&self, arxiv_id: &str, authors: &[ArxivAuthor]) -> AppResult<usize> {
+        if authors.is_empty() {
+            return Ok(0);
+        }
+
+        let existing = self.select_paper_authors(arxiv_id)?;
+        if existing == authors {
+            return Ok(0);
+        }
+
+        let mut stmt = self.conn.prepare_cached("DELETE FROM paper_author WHERE arxiv_id = :arxiv_id")?;
+        stmt.execute(named_params! { ":arxiv_id": arxiv_id })?;
+        self.insert_paper_authors(arxiv_id, authors)?;
+
+        Ok(existing.len().max(authors.len()))
+    }
+
+    /// selects the paper's authors in author-list order
+    pub fn select_paper_authors(&self, arxiv_id: &str) -> AppResult<Vec<ArxivAuthor>> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT author.last_name, author.first_names, author.suffix
+        FROM paper_author
+        INNER JOIN author ON author.id = paper_author.author_id
+        WHERE paper_author.arxiv_id = :arxiv_id
+        ORDER BY paper_author.position
+        ")?;
+
+        let authors = stmt.query_map(named_params! { ":arxiv_id": arxiv_id }, map_author_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(authors)
+    }
+
+    /// counts papers per author, most prolific first
+    pub fn count_papers_per_author(&self, limit: u64) -> AppResult<Vec<(ArxivAuthor, u64)>> {
+        let mut stmt = self.conn.prepare(r"
+        SELECT author.last_name, author.first_names, author.suffix, COUNT(DISTINCT paper_author.arxiv_id) AS paper_count
+        FROM author
+        INNER JOIN paper_author ON paper_author.author_id = author.id
+        GROUP BY author.id
+        ORDER BY paper_count DESC
+        LIMIT :limit
+        ")?;
+
+        let counts = stmt.query_map(named_params! { ":limit": limit }, |row| {
+            Ok((map_author_row(row)?, row.get::<_, u64>("paper_count")?))
+        })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(counts)
+    }
+
+    /// files the paper under each category. The first category is marked as primary.
+    pub fn insert_paper_categories(&self, arxiv_id: &str, categories: &[&str]) -> AppResult<()> {
+        let mut stmt = self.conn.prepare_cached(r"
+        INSERT OR IGNORE INTO paper_category (arxiv_id, category, position, is_primary)
+        VALUES (:arxiv_id, :category, :position, :is_primary)
+        ")?;
+
+        for (position, category) in categories.iter().enumerate() {
+            let params = named_params! {
+                ":arxiv_id": arxiv_id,
+                ":category": category,
+                ":position": position as i64,
+                ":is_primary": position == 0,
+            };
+            stmt.execute(params)?;
+        }
+
+        Ok(())
+    }
+
+    /// replaces the paper's categories if they differ from the given list. Returns the number of
+    /// changed rows.
+    pub fn sync_paper_categories(&self, arxiv_id: &str, categories: &[&str]) -> AppResult<usize> {
+        let existing = self.select_paper_categories(arxiv_id)?;
+        if existing == categories {
+            return Ok(0);
+        }
+
+        let mut stmt = self.conn.prepare_cached("DELETE FROM paper_category WHERE arxiv_id = :arxiv_id")?;
+        stmt.execute(named_params! { ":arxiv_id": arxiv_id })?;
+        self.insert_paper_categories(arxiv_id, categories)?;
+
+        Ok(existing.len().max(categories.len()))
+    }
+
+    /// selects the paper's categories, primary first
+    pub fn select_paper_categories(&self, arxiv_id: &str) -> AppResult<Vec<String>> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT category FROM paper_category WHERE arxiv_id = :arxiv_id ORDER BY position
+        ")?;
+
+        let categories = stmt.query_map(named_params! { ":arxiv_id": arxiv_id }, |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(categories)
+    }
+
+    /// fills paper_category for metadata rows loaded before the table existed. Returns the number
+    /// of inserted rows.
+    pub fn backfill_paper_categories(&self) -> AppResult<usize> {
+        let inserted = self.conn.execute(r"
+        INSERT OR IGNORE INTO paper_category (arxiv_id, category, position, is_primary)
+        WITH RECURSIVE split(arxiv_id, category, rest, position) AS (
+            SELECT id, '', trim(categories) || ' ', -1
+            FROM arxiv_metadata
+            WHERE categories IS NOT NULL AND NOT EXISTS (
+                SELECT 1 FROM paper_category WHERE paper_category.arxiv_id = arxiv_metadata.id
+            )
+            UNION ALL
+            SELECT arxiv_id, substr(rest, 1, instr(rest, ' ') - 1), ltrim(substr(rest, instr(rest, ' ') + 1)), position + 1
+            FROM split
+            WHERE rest <> ''
+        )
+        SELECT arxiv_id, category, position, position = 0 FROM split WHERE category <> ''
+        ", [])?;
+
+        Ok(inserted)
+    }
+
+    /// counts papers per category, largest first. With `primary_only`, a paper only counts
+    /// towards its primary category.
+    pub fn count_papers_by_category(&self, primary_only: bool) -> AppResult<Vec<CategoryCount>> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT paper_category.category, category.name, COUNT(*) AS paper_count
+        FROM paper_category
+        LEFT JOIN category ON category.code = paper_category.category
+        WHERE paper_category.is_primary OR NOT :primary_only
+        GROUP BY paper_category.category
+        ORDER BY paper_count DESC, paper_category.category
+        ")?;
+
+        let counts = stmt.query_map(named_params! { ":primary_only": primary_only }, |row| {
+            Ok(CategoryCount {
+                code: row.get("category")?,
+                name: row.get("name")?,
+                paper_count: row.get("paper_count")?,
+            })
+        })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(counts)
+    }
+
+    pub fn count_papers_in_category(&self, category: &str, primary_only: bool) -> AppResult<u64> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT COUNT(*) FROM paper_category
+        WHERE category = :category AND (is_primary OR NOT :primary_only)
+        ")?;
+        let params = named_params! {
+            ":category": category,
+            ":primary_only": primary_only,
+        };
+
+        Ok(stmt.query_row(params, |row| row.get::<_, u64>(0))?)
+    }
+
+    pub fn select_arxiv_ids_in_category(&self, category: &str, primary_only: bool) -> AppResult<Vec<ArxivId>> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT arxiv_id FROM paper_category
+        WHERE category = :category AND (is_primary OR NOT :primary_only)
+        ORDER BY arxiv_id
+        ")?;
+        let params = named_params! {
+            ":category": category,
+            ":primary_only": primary_only,
+        };
+
+        let ids = stmt.query_map(params, |row| row.get::<_, ArxivId>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ids)
+    }
+
+    /// parses raw `created` strings into `created_utc` where it is missing. Returns the number of
+    /// updated versions.
+    pub fn backfill_version_timestamps(&self) -> AppResult<usize> {
+        let mut select_stmt = self.conn.prepare(r"
+        SELECT id, created FROM arxiv_version WHERE created_utc IS NULL AND created IS NOT NULL
+        ")?;
+        let mut update_stmt = self.conn.prepare("UPDATE arxiv_version SET created_utc = :created_utc WHERE id = :id")?;
+
+        let pending = select_stmt.query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut updated = 0;
+        for (id, created) in pending {
+            let Ok(created) = chrono::DateTime::parse_from_rfc2822(&created) else {
+                continue;
+            };
+
+            updated += update_stmt.execute(named_params! {
+                ":id": id,
+                ":created_utc": to_iso8601_utc(&created),
+            })?;
+        }
+
+        Ok(updated)
+    }
+
+    /// selects papers whose first or latest version was created within the date range
+    pub fn select_arxiv_ids_by_version_date(&self, version: VersionSelector, range: &DateRange) -> AppResult<Vec<ArxivId>> {
+        let (since, until) = version_date_bounds(range);
+        let mut stmt = self.conn.prepare_cached(&version_date_query("arxiv_id", version))?;
+        let ids = stmt.query_map(named_params! { ":since": since, ":until": until }, |row| row.get::<_, ArxivId>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(ids)
+    }
+
+    /// counts papers whose first or latest version was created within the date range
+    pub fn count_arxiv_ids_by_version_date(&self, version: VersionSelector, range: &DateRange) -> AppResult<u64> {
+        let query = format!("SELECT COUNT(*) FROM ({})", version_date_query("arxiv_id", version));
+        let (since, until) = version_date_bounds(range);
+        let mut stmt = self.conn.prepare_cached(&query)?;
+        let count = stmt.query_row(named_params! { ":since": since, ":until": until }, |row| row.get::<_, u64>(0))?;
+
+        Ok(count)
+    }
+
     pub fn insert_content(&self, arxiv_id: &str, content: ArxivPaperContent) -> AppResult<()> {
         let mut stmt = self.conn.prepare_cached(r"
-        INSERT INTO paper_data (arxiv_id, abstract, keywords, content)
-        VALUES (:arxiv_id, :abstract, :keywords, :content)
+        INSERT INTO paper_data (
+            arxiv_id, abstract, content, keyword_strategy, body_start_pattern, body_end_pattern,
+            extractor_version, extracted_utc, source_object, source_generation
+        )
+        VALUES (
+            :arxiv_id, :abstract, :content, :keyword_strategy, :body_start_pattern, :body_end_pattern,
+            :extractor_version, :extracted_utc, :source_object, :source_generation
+        )
         ")?;
 
+        let provenance = content.provenance.as_ref();
         let params = named_params! {
             ":arxiv_id": arxiv_id,
             ":abstract": content.abstract_text,
-            ":keywords": content.keywords.join(","),
-            ":content": content.paper_content
+            ":content": content.paper_content,
+            ":keyword_strategy": provenance.map(|p| p.keyword_strategy.as_str()),
+            ":body_start_pattern": provenance.map(|p| p.body_boundaries.start_pattern.as_str()),
+            ":body_end_pattern": provenance.map(|p| p.body_boundaries.end_pattern.as_str()),
+            ":extractor_version": provenance.map(|p| p.extractor_version),
+            ":extracted_utc": provenance.map(|p| p.extracted_utc.as_str()),
+            ":source_object": provenance.and_then(|p| p.source_object.as_deref()),
+            ":source_generation": provenance.and_then(|p| p.source_generation),
         };
 
         stmt.execute(params)?;
+        self.insert_paper_keywords(arxiv_id, &content.keywords)?;
 
         Ok(())
     }
@@ -195,30 +954,204 @@ impl<'a> ArxivDBQueries<'a> {
     pub fn update_keywords_and_content(&self, content: ArxivPaperContent) -> AppResult<()> {
         let mut stmt = self.conn.prepare_cached(r"
         UPDATE paper_data
-        SET keywords = :keywords, content = :content
+        SET
+            content = :content,
+            keyword_strategy = :keyword_strategy,
+            body_start_pattern = :body_start_pattern,
+            body_end_pattern = :body_end_pattern,
+            extractor_version = :extractor_version,
+            extracted_utc = :extracted_utc,
+            source_object = :source_object,
+            source_generation = :source_generation
         WHERE arxiv_id = :arxiv_id
         ")?;
         
+        let provenance = content.provenance.as_ref();
         let params = named_params! {
-            ":keywords": content.keywords.join(","),
             ":content": content.paper_content,
+            ":keyword_strategy": provenance.map(|p| p.keyword_strategy.as_str()),
+            ":body_start_pattern": provenance.map(|p| p.body_boundaries.start_pattern.as_str()),
+            ":body_end_pattern": provenance.map(|p| p.body_boundaries.end_pattern.as_str()),
+            ":extractor_version": provenance.map(|p| p.extractor_version),
+            ":extracted_utc": provenance.map(|p| p.extracted_utc.as_str()),
+            ":source_object": provenance.and_then(|p| p.source_object.as_deref()),
+            ":source_generation": provenance.and_then(|p| p.source_generation),
             ":arxiv_id": content.id
         };
         
         stmt.execute(params)?;
+        self.replace_paper_keywords(&content.id, &content.keywords)?;
         Ok(())
     }
 
+    /// stores the text of a paper version, replacing any text stored for it before
+    pub fn insert_paper_text(&self, text: &PaperText) -> AppResult<()> {
+        let compressed = zstd::encode_all(text.text.as_bytes(), TEXT_COMPRESSION_LEVEL)?;
+        let mut stmt = self.conn.prepare_cached(r"
+        INSERT OR REPLACE INTO paper_text (
+            arxiv_id, version, compression, text, text_bytes, source_object, source_generation, stored_utc
+        )
+        VALUES (
+            :arxiv_id, :version, :compression, :text, :text_bytes, :source_object, :source_generation, :stored_utc
+        )
+        ")?;
+
+        stmt.execute(named_params! {
+            ":arxiv_id": text.arxiv_id,
+            ":version": text.version.unwrap_or(0),
+            ":compression": TEXT_COMPRESSION,
+            ":text": compressed,
+            ":text_bytes": text.text.len(),
+            ":source_object": text.source_object,
+            ":source_generation": text.source_generation,
+            ":stored_utc": to_iso8601_utc(&Utc::now()),
+        })?;
+        Ok(())
+    }
+
+    /// the stored text of the latest stored version of a paper
+    pub fn select_paper_text(&self, arxiv_id: &str) -> AppResult<Option<PaperText>> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT version, compression, text, source_object, source_generation
+        FROM paper_text
+        WHERE arxiv_id = :arxiv_id
+        ORDER BY version DESC
+        LIMIT 1
+        ")?;
+
+        let row = stmt.query_row(named_params! { ":arxiv_id": arxiv_id }, |row| {
+            Ok((
+                row.get::<_, u32>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Vec<u8>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<i64>>(4)?,
+            ))
+        }).optional()?;
+
+        let Some((version, compression, compressed, source_object, source_generation)) = row else {
+            return Ok(None);
+        };
+        if compression != TEXT_COMPRESSION {
+            return Err(AppError::Other(format!("stored text of {} uses unknown compression '{}'", arxiv_id, compression)));
+        }
+
+        let text = String::from_utf8(zstd::decode_all(compressed.as_slice())?)
+            .map_err(|err| err.utf8_error())?;
+        Ok(Some(PaperText {
+            arxiv_id: arxiv_id.to_string(),
+            version: Some(version).filter(|version| *version > 0),
+            text,
+            source_object,
+            source_generation,
+        }))
+    }
+
+    pub fn count_papers_with_text(&self) -> AppResult<u64> {
+        let count = self.conn.query_row("SELECT COUNT(DISTINCT arxiv_id) FROM paper_text", [], |row| row.get::<_, u64>(0))?;
+        Ok(count)
+    }
+
+    /// one keyset page of ids of papers with stored text
+    pub fn select_arxiv_ids_with_text(&self, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT DISTINCT arxiv_id FROM paper_text
+        WHERE arxiv_id > :after
+        ORDER BY arxiv_id
+        LIMIT :limit
+        ")?;
+
+        let ids = stmt.query_map(named_params! { ":after": page.after, ":limit": page.limit }, |row| row.get::<_, ArxivId>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+
+    /// counts papers whose keywords were found by `strategy`, leaving out extractions made by
+    /// `current_version` if given, so rows from outdated logic can be re-run
+    pub fn count_arxiv_ids_by_keyword_strategy(&self, strategy: KeywordStrategy, current_version: Option<&str>) -> AppResult<u64> {
+        let count = self.conn.query_row(r"
+        SELECT COUNT(*) FROM paper_data
+        WHERE keyword_strategy = :strategy
+            AND (:current_version IS NULL OR extractor_version IS NOT :current_version)
+        ", named_params! { ":strategy": strategy.as_str(), ":current_version": current_version }, |row| row.get::<_, u64>(0))?;
+        Ok(count)
+    }
+
+    /// one keyset page of the papers [Self::count_arxiv_ids_by_keyword_strategy] counts
+    pub fn select_arxiv_ids_by_keyword_strategy(&self, strategy: KeywordStrategy, current_version: Option<&str>, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT arxiv_id FROM paper_data
+        WHERE keyword_strategy = :strategy
+            AND (:current_version IS NULL OR extractor_version IS NOT :current_version)
+            AND arxiv_id > :after
+        ORDER BY arxiv_id
+        LIMIT :limit
+        ")?;
+
+        let params = named_params! {
+            ":strategy": strategy.as_str(),
+            ":current_version": current_version,
+            ":after": page.after,
+            ":limit": page.limit,
+        };
+        let ids = stmt.query_map(params, |row| row.get::<_, ArxivId>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+
+    /// stores keywords in order, alongside their normalized form. Blank keywords are dropped.
+    pub fn insert_paper_keywords(&self, arxiv_id: &str, keywords: &[String]) -> AppResult<()> {
+        let mut stmt = self.conn.prepare_cached(r"
+        INSERT INTO paper_keyword (arxiv_id, position, keyword, normalized)
+        VALUES (:arxiv_id, :position, :keyword, :normalized)
+        ")?;
+
+        let keywords = keywords.iter()
+            .map(|keyword| keyword.trim())
+            .filter(|keyword| !keyword.is_empty());
+        for (position, keyword) in keywords.enumerate() {
+            stmt.execute(named_params! {
+                ":arxiv_id": arxiv_id,
+                ":position": position,
+                ":keyword": keyword,
+                ":normalized": normalize_keyword(keyword),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// replaces all keywords of a paper
+    pub fn replace_paper_keywords(&self, arxiv_id: &str, keywords: &[String]) -> AppResult<()> {
+        let mut stmt = self.conn.prepare_cached("DELETE FROM paper_keyword WHERE arxiv_id = :arxiv_id")?;
+        stmt.execute(named_params! { ":arxiv_id": arxiv_id })?;
+        self.insert_paper_keywords(arxiv_id, keywords)
+    }
+
+    /// keywords of a paper in the order they appear in the paper
+    pub fn select_paper_keywords(&self, arxiv_id: &str) -> AppResult<Vec<String>> {
+        let mut stmt = self.conn.prepare_cached(r"
+        SELECT keyword FROM paper_keyword
+        WHERE arxiv_id = :arxiv_id
+        ORDER BY position
+        ")?;
+
+        let keywords = stmt.query_map(named_params! { ":arxiv_id": arxiv_id }, |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(keywords)
+    }
+
     pub fn insert_versions(&self, arxiv_id: &str, metadata: &[ArxivVersion]) -> AppResult<()> {
         let mut stmt = self.conn.prepare_cached(r"
-        INSERT INTO arxiv_version(arxiv_id, version, created)
-        VALUES (:arxiv_id, :version, :created)
+        INSERT INTO arxiv_version(arxiv_id, version, created, created_utc)
+        VALUES (:arxiv_id, :version, :created, :created_utc)
         ")?;
         for item in metadata {
             let params = named_params! {
                 ":arxiv_id": arxiv_id,
                 ":version": item.version(),
-                ":created": item.created()
+                ":created": item.created(),
+                ":created_utc": item.created_utc()
             };
 
             stmt.execute(params)?;
@@ -228,16 +1161,20 @@ impl<'a> ArxivDBQueries<'a> {
     }
 
     pub fn insert_metadata(&self, metadata: &ArxivMetadata) -> AppResult<()> {
-        let mut stmt = self.conn.prepare_cached("INSERT INTO arxiv_metadata VALUES (:id,:submitted,:authors,:title,:comments,:journal_ref,:doi,:categories)")?;
+        let mut stmt = self.conn.prepare_cached(r"
+        INSERT INTO arxiv_metadata (id, submitted, authors, title, comments, journal_ref, doi, categories, update_date)
+        VALUES (:id, :submitted, :authors, :title, :comments, :journal_ref, :doi, :categories, :update_date)
+        ")?;
         let params = named_params! {
-            ":id": metadata.id(),
+            ":id": metadata.arxiv_id()?.base_id(),
             ":submitted": metadata.submitter(),
             ":authors": metadata.authors(),
             ":title": metadata.title(),
             ":comments": metadata.comments(),
             ":journal_ref": metadata.journal_ref(),
             ":doi": metadata.doi(),
-            ":categories": metadata.categories()
+            ":categories": metadata.categories(),
+            ":update_date": metadata.update_date()
         };
 
         stmt.execute(params)?;
@@ -245,3 +1182,144 @@ impl<'a> ArxivDBQueries<'a> {
         Ok(())
     }
 }
+
+fn map_author_row(row: &Row) -> rusqlite::Result<ArxivAuthor> {
+    Ok(ArxivAuthor {
+        last_name: row.get("last_name")?,
+        first_names: row.get("first_names")?,
+        suffix: row.get("suffix")?,
+    })
+}
+
+/// restricts a training_data query to papers matching the `:search` full-text query. Without a
+/// query the condition still mentions `:search`, so both variants take the same parameters.
+fn search_condition(search: Option<&str>) -> &'static str {
+    match search {
+        Some(_) => "arxiv_id IN (SELECT arxiv_id FROM paper_search WHERE paper_search MATCH :search)",
+        None => ":search IS NULL",
+    }
+}
+
+/// groups versions per paper and keeps papers whose first or latest `created_utc` falls in
+/// `[:since, :until)`. Open bounds are passed as NULL.
+fn version_date_query(columns: &str, version: VersionSelector) -> String {
+    let aggregate = match version {
+        VersionSelector::First => "MIN",
+        VersionSelector::Last => "MAX",
+    };
+
+    format!(r"
+    SELECT {columns} FROM arxiv_version
+    WHERE created_utc IS NOT NULL
+    GROUP BY arxiv_id
+    HAVING (:since IS NULL OR {aggregate}(created_utc) >= :since)
+       AND (:until IS NULL OR {aggregate}(created_utc) < :until)
+    ORDER BY arxiv_id
+    ")
+}
+
+fn version_date_bounds(range: &DateRange) -> (Option<String>, Option<String>) {
+    (range.since.as_ref().map(to_iso8601_utc), range.until.as_ref().map(to_iso8601_utc))
+}
+
+/// binds the base id, which every table keys on; the version is dropped
+impl ToSql for ArxivId {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.base_id()))
+    }
+}
+
+impl FromSql for ArxivId {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let id = value.as_str()?;
+        ArxivId::parse(id).map_err(|err| FromSqlError::Other(Box::new(err)))
+    }
+}
+
+fn optional_text(value: Option<&String>) -> Value {
+    value.cloned().map(Value::from).unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::body::BodyBoundaries;
+    use crate::content::ExtractionProvenance;
+    use crate::storage::PaperStore;
+
+    fn metadata(title: &str, abstract_text: &str, versions: &[&str]) -> ArxivMetadata {
+        let versions = versions.iter()
+            .enumerate()
+            .map(|(day, version)| serde_json::json!({ "version": version, "created": format!("Mon, {} Jan 2021 10:00:00 GMT", day + 4) }))
+            .collect::<Vec<_>>();
+        serde_json::from_value(serde_json::json!({
+            "id": "2101.00001",
+            "title": title,
+            "abstract": abstract_text,
+            "categories": "cs.LG",
+            "versions": versions,
+        })).unwrap()
+    }
+
+    fn versions(queries: &ArxivDBQueries) -> Vec<(i64, String)> {
+        queries.conn.prepare("SELECT id, version FROM arxiv_version WHERE arxiv_id = '2101.00001' ORDER BY id").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<Result<Vec<_>, _>>().unwrap()
+    }
+
+    #[test]
+    fn upsert_keeps_extracted_content() {
+        let mut db = ArxivDB::open(":memory:").unwrap();
+        db.migrate().unwrap();
+        let queries = db.queries();
+
+        let outcome = queries.upsert_arxiv_metadata(metadata("A title", "An abstract.", &["v1"])).unwrap();
+        assert_eq!(outcome, UpsertOutcome::Inserted);
+        queries.upsert_content(ArxivPaperContent {
+            id: "2101.00001".to_string(),
+            abstract_text: "An abstract.".to_string(),
+            keywords: vec!["graphs".to_string(), "sparsity".to_string()],
+            paper_content: "A body.".to_string(),
+            provenance: Some(ExtractionProvenance {
+                keyword_strategy: KeywordStrategy::SmallLabel,
+                body_boundaries: BodyBoundaries {
+                    start_pattern: "intro".to_string(),
+                    end_pattern: "references".to_string(),
+                },
+                extractor_version: "keyword-dataset-rs/0.1.0",
+                extracted_utc: "2021-01-04T10:00:00Z".to_string(),
+                source_object: None,
+                source_generation: None,
+            }),
+        }).unwrap();
+        PaperStore::insert_extraction_result(&queries, "2101.00001", None, Duration::from_millis(10)).unwrap();
+        let original_versions = versions(&queries);
+
+        // a newer snapshot with a revised title and abstract and a second version
+        let revised = || metadata("A revised title", "A revised abstract.", &["v1", "v2"]);
+        assert_eq!(queries.upsert_arxiv_metadata(revised()).unwrap(), UpsertOutcome::Updated);
+        assert_eq!(queries.upsert_arxiv_metadata(revised()).unwrap(), UpsertOutcome::Unchanged);
+
+        let versions = versions(&queries);
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0], original_versions[0]);
+        assert_eq!(versions[1].1, "v2");
+
+        let (title, abstract_text, content, strategy) = queries.conn.query_row(
+            "SELECT title, abstract, content, keyword_strategy FROM arxiv_metadata JOIN paper_data ON paper_data.arxiv_id = arxiv_metadata.id",
+            [],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+        ).unwrap();
+        assert_eq!(title, "A revised title");
+        assert_eq!(abstract_text, "A revised abstract.");
+        assert_eq!((content.as_str(), strategy.as_str()), ("A body.", "small_label"));
+        assert_eq!(queries.select_paper_keywords("2101.00001").unwrap(), vec!["graphs", "sparsity"]);
+
+        let (status, attempts) = queries.conn.query_row(
+            "SELECT status_code, attempts FROM extraction_result WHERE arxiv_id = '2101.00001'",
+            [],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?))
+        ).unwrap();
+        assert_eq!((status.as_str(), attempts), ("OK", 1));
+    }
+}
@@ -0,0 +1,289 @@
+use log::info;
+use serde::Serialize;
+use crate::db::ArxivDBQueries;
+use crate::err::AppResult;
+
+/// one kind of inconsistency that `db check` looks for, and how `--repair` fixes it
+pub struct IntegrityCheck {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// what a repair does to the offending rows
+    pub repair: &'static str,
+    /// counts the offending rows
+    count_sql: &'static str,
+    /// fixes the offending rows in a single statement. `None` for problems that can only be
+    /// reported, because fixing them needs data the database doesn't have.
+    repair_sql: Option<&'static str>,
+}
+
+impl IntegrityCheck {
+    pub fn is_repairable(&self) -> bool {
+        self.repair_sql.is_some()
+    }
+}
+
+/// The schema the checks run against. `db check` stops migrating here, because later migrations
+/// need the repairs: 0012 makes duplicate `paper_data` rows impossible, so they have to be found
+/// and reported before it runs.
+pub const CHECK_SCHEMA_VERSION: u32 = 11;
+
+/// every check, in the order repairs are applied. Orphans go first so they don't count as
+/// duplicates.
+pub const INTEGRITY_CHECKS: &[IntegrityCheck] = &[
+    IntegrityCheck {
+        name: "orphaned_versions",
+        description: "versions of papers missing from arxiv_metadata",
+        repair: "deleted",
+        count_sql: "SELECT COUNT(*) FROM arxiv_version WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)",
+        repair_sql: Some("DELETE FROM arxiv_version WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)"),
+    },
+    IntegrityCheck {
+        name: "orphaned_results",
+        description: "extraction attempts for papers missing from arxiv_metadata",
+        repair: "deleted",
+        count_sql: "SELECT COUNT(*) FROM extraction_attempt WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)",
+        repair_sql: Some("DELETE FROM extraction_attempt WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)"),
+    },
+    IntegrityCheck {
+        name: "orphaned_paper_data",
+        description: "paper_data rows for papers missing from arxiv_metadata",
+        repair: "deleted",
+        count_sql: "SELECT COUNT(*) FROM paper_data WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)",
+        repair_sql: Some("DELETE FROM paper_data WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)"),
+    },
+    IntegrityCheck {
+        name: "orphaned_keywords",
+        description: "keywords of papers missing from arxiv_metadata",
+        repair: "deleted",
+        count_sql: "SELECT COUNT(*) FROM paper_keyword WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)",
+        repair_sql: Some("DELETE FROM paper_keyword WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)"),
+    },
+    IntegrityCheck {
+        name: "orphaned_authors",
+        description: "author links of papers missing from arxiv_metadata",
+        repair: "deleted",
+        count_sql: "SELECT COUNT(*) FROM paper_author WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)",
+        repair_sql: Some("DELETE FROM paper_author WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)"),
+    },
+    IntegrityCheck {
+        name: "orphaned_categories",
+        description: "category links of papers missing from arxiv_metadata",
+        repair: "deleted",
+        count_sql: "SELECT COUNT(*) FROM paper_category WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)",
+        repair_sql: Some("DELETE FROM paper_category WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)"),
+    },
+    IntegrityCheck {
+        name: "orphaned_texts",
+        description: "stored PDF text of papers missing from arxiv_metadata",
+        repair: "deleted",
+        count_sql: "SELECT COUNT(*) FROM paper_text WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)",
+        repair_sql: Some("DELETE FROM paper_text WHERE arxiv_id NOT IN (SELECT id FROM arxiv_metadata)"),
+    },
+    IntegrityCheck {
+        name: "duplicate_paper_data",
+        // only finds anything before migration 0012, which refuses to run while there are any
+        description: "extra paper_data rows for the same paper",
+        repair: "deleted, keeping the row with the longest content, then one with provenance, then the oldest",
+        count_sql: r"
+        SELECT COUNT(*) FROM paper_data
+        WHERE id <> (
+            SELECT keep.id FROM paper_data AS keep
+            WHERE keep.arxiv_id = paper_data.arxiv_id
+            ORDER BY COALESCE(LENGTH(keep.content), 0) DESC, keep.extractor_version IS NULL, keep.id
+            LIMIT 1
+        )",
+        repair_sql: Some(r"
+        DELETE FROM paper_data
+        WHERE id <> (
+            SELECT keep.id FROM paper_data AS keep
+            WHERE keep.arxiv_id = paper_data.arxiv_id
+            ORDER BY COALESCE(LENGTH(keep.content), 0) DESC, keep.extractor_version IS NULL, keep.id
+            LIMIT 1
+        )"),
+    },
+    IntegrityCheck {
+        name: "missing_paper_data",
+        description: "papers without a paper_data row",
+        repair: "not repaired, since their abstracts are gone; run `db load --incremental` with the metadata dump to restore them",
+        count_sql: "SELECT COUNT(*) FROM arxiv_metadata WHERE id NOT IN (SELECT arxiv_id FROM paper_data)",
+        repair_sql: None,
+    },
+    IntegrityCheck {
+        name: "ok_without_content",
+        description: "papers whose latest extraction is OK but have no extracted content",
+        repair: "given a new EMPTY_CONTENT attempt, so `extract --pending` picks them up again",
+        count_sql: r"
+        SELECT COUNT(*) FROM extraction_result
+        WHERE status_code = 'OK' AND NOT EXISTS (
+            SELECT 1 FROM paper_data
+            WHERE paper_data.arxiv_id = extraction_result.arxiv_id AND LENGTH(paper_data.content) > 0
+        )",
+        repair_sql: Some(r"
+        INSERT INTO extraction_attempt (arxiv_id, attempt, status_code, status_msg, attempted_utc)
+        SELECT arxiv_id, attempts + 1, 'EMPTY_CONTENT', 'OK result without content, reset by db check', strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+        FROM extraction_result
+        WHERE status_code = 'OK' AND NOT EXISTS (
+            SELECT 1 FROM paper_data
+            WHERE paper_data.arxiv_id = extraction_result.arxiv_id AND LENGTH(paper_data.content) > 0
+        )"),
+    },
+];
+
+/// the outcome of one [IntegrityCheck]
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckFinding {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// offending rows found
+    pub found: u64,
+    /// rows the repair changed, if it ran. Checks without a repair never run one.
+    pub repaired: Option<u64>,
+}
+
+impl ArxivDBQueries<'_> {
+    /// runs every [IntegrityCheck] without changing anything
+    pub fn check_integrity(&self) -> AppResult<Vec<CheckFinding>> {
+        INTEGRITY_CHECKS.iter()
+            .map(|check| Ok(CheckFinding {
+                name: check.name,
+                description: check.description,
+                found: self.count_check(check)?,
+                repaired: None,
+            }))
+            .collect()
+    }
+
+    /// runs every [IntegrityCheck] and repairs what it can, all in one transaction
+    pub fn repair_integrity(&self) -> AppResult<Vec<CheckFinding>> {
+        self.transaction(|queries| {
+            let mut findings = Vec::with_capacity(INTEGRITY_CHECKS.len());
+            for check in INTEGRITY_CHECKS {
+                let found = queries.count_check(check)?;
+                let repaired = match check.repair_sql {
+                    Some(repair_sql) if found > 0 => {
+                        let changed = queries.conn.execute(repair_sql, [])? as u64;
+                        info!("{}: {} row(s) {}", check.name, changed, check.repair);
+                        Some(changed)
+                    }
+                    Some(_) => Some(0),
+                    None => None,
+                };
+
+                findings.push(CheckFinding {
+                    name: check.name,
+                    description: check.description,
+                    found,
+                    repaired,
+                });
+            }
+
+            Ok(findings)
+        })
+    }
+
+    fn count_check(&self, check: &IntegrityCheck) -> AppResult<u64> {
+        Ok(self.conn.query_row(check.count_sql, [], |row| row.get::<_, u64>(0))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::ArxivDB;
+    use crate::err::AppError;
+
+    /// a database at [CHECK_SCHEMA_VERSION] with most of the problems the checks look for, the way
+    /// loads without foreign keys or a unique index left them
+    fn seeded_db() -> ArxivDB {
+        let mut db = ArxivDB::open(":memory:").unwrap();
+        db.migrate_to(CHECK_SCHEMA_VERSION).unwrap();
+        db.queries().conn.execute_batch(r"
+            PRAGMA foreign_keys = OFF;
+            INSERT INTO arxiv_metadata (id) VALUES ('2101.00001'), ('2101.00002'), ('2101.00003'), ('2101.00004');
+            -- a duplicate, where the later row has the content
+            INSERT INTO paper_data (id, arxiv_id, abstract, content) VALUES (1, '2101.00001', 'A.', '');
+            INSERT INTO paper_data (id, arxiv_id, abstract, content, extractor_version) VALUES (2, '2101.00001', 'A.', 'Body.', '0.1.0');
+            -- OK without content
+            INSERT INTO paper_data (id, arxiv_id, abstract, content) VALUES (3, '2101.00002', 'B.', '');
+            -- 2101.00003 has no paper_data
+            INSERT INTO paper_data (id, arxiv_id, abstract, content) VALUES (4, '2101.00004', 'D.', 'Body.');
+            INSERT INTO extraction_attempt (arxiv_id, attempt, status_code) VALUES
+                ('2101.00001', 1, 'OK'), ('2101.00002', 1, 'OK'), ('2101.00004', 1, 'OK');
+            -- rows of a paper that is gone
+            INSERT INTO arxiv_version (arxiv_id, version) VALUES ('9901.00001', 'v1');
+            INSERT INTO extraction_attempt (arxiv_id, attempt, status_code) VALUES ('9901.00001', 1, 'NETWORK');
+            INSERT INTO paper_data (arxiv_id, abstract, content) VALUES ('9901.00001', 'Gone.', 'Body.');
+            INSERT INTO paper_keyword (arxiv_id, position, keyword, normalized) VALUES ('9901.00001', 0, 'gone', 'gone');
+            PRAGMA foreign_keys = ON;
+        ").unwrap();
+        db
+    }
+
+    fn counts(findings: &[CheckFinding]) -> Vec<(&'static str, u64, Option<u64>)> {
+        findings.iter()
+            .filter(|finding| finding.found > 0)
+            .map(|finding| (finding.name, finding.found, finding.repaired))
+            .collect()
+    }
+
+    #[test]
+    fn checks_and_repairs() {
+        let mut db = seeded_db();
+        let expected = vec![
+            ("orphaned_versions", 1, None),
+            ("orphaned_results", 1, None),
+            ("orphaned_paper_data", 1, None),
+            ("orphaned_keywords", 1, None),
+            ("duplicate_paper_data", 1, None),
+            ("missing_paper_data", 1, None),
+            ("ok_without_content", 1, None),
+        ];
+        assert_eq!(counts(&db.queries().check_integrity().unwrap()), expected);
+
+        let repaired = db.queries().repair_integrity().unwrap();
+        assert_eq!(counts(&repaired), vec![
+            ("orphaned_versions", 1, Some(1)),
+            ("orphaned_results", 1, Some(1)),
+            ("orphaned_paper_data", 1, Some(1)),
+            ("orphaned_keywords", 1, Some(1)),
+            ("duplicate_paper_data", 1, Some(1)),
+            ("missing_paper_data", 1, None),
+            ("ok_without_content", 1, Some(1)),
+        ]);
+        // checks that found nothing still report a repair of no rows
+        assert!(repaired.iter()
+            .filter(|finding| finding.found == 0)
+            .all(|finding| finding.repaired == Some(0)));
+
+        let conn = db.queries().conn;
+        let kept = conn.query_row("SELECT id FROM paper_data WHERE arxiv_id = '2101.00001'", [], |row| row.get::<_, i64>(0)).unwrap();
+        assert_eq!(kept, 2);
+        let (status, attempts) = conn.query_row(
+            "SELECT status_code, attempts FROM extraction_result WHERE arxiv_id = '2101.00002'",
+            [],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?))
+        ).unwrap();
+        assert_eq!((status.as_str(), attempts), ("EMPTY_CONTENT", 2));
+
+        // only the problem that can't be repaired is left
+        assert_eq!(counts(&db.queries().check_integrity().unwrap()), vec![("missing_paper_data", 1, None)]);
+        db.migrate().unwrap();
+        assert!(db.schema_status().unwrap().is_current());
+    }
+
+    #[test]
+    fn duplicates_block_unique_index() {
+        let mut db = seeded_db();
+        match db.migrate() {
+            Err(AppError::MigrationBlocked { migration, .. }) => assert_eq!(migration, "0012_unique_paper_data"),
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("migrated despite duplicates"),
+        }
+
+        // nothing is lost, and `db check` can still report the duplicates
+        assert_eq!(db.schema_status().unwrap().user_version, CHECK_SCHEMA_VERSION);
+        let findings = db.queries().check_integrity().unwrap();
+        let duplicates = findings.iter().find(|finding| finding.name == "duplicate_paper_data").unwrap();
+        assert_eq!(duplicates.found, 1);
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt::{Display, Formatter};
+use log::info;
+use rusqlite::{named_params, Connection};
+use crate::db::ArxivDBQueries;
+use crate::err::{AppError, AppResult};
+
+/// returns why a migration can't run yet, if it can't
+type PreMigrateFn = fn(&ArxivDBQueries) -> AppResult<Option<String>>;
+
+/// one step of the schema history. Migrations are applied in order, each in its own transaction,
+/// and `PRAGMA user_version` records the last one applied.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub sql: &'static str,
+    /// checked before the script, in the same transaction. Returns why the data would make the
+    /// script fail or lose rows, and the migration doesn't run.
+    pre_migrate: Option<PreMigrateFn>,
+    /// data fix-up to run after the script, in the same transaction
+    post_migrate: Option<fn(&ArxivDBQueries) -> AppResult<()>>,
+}
+
+impl Display for Migration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}_{}", self.version, self.name)
+    }
+}
+
+/// every migration known to this binary, ordered by version. Never edit a migration that has been
+/// released; add a new one instead.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../../sql/migrations/0001_initial.sql"),
+        pre_migrate: None,
+        post_migrate: None,
+    },
+    Migration {
+        version: 2,
+        name: "authors",
+        sql: include_str!("../../sql/migrations/0002_authors.sql"),
+        pre_migrate: None,
+        post_migrate: None,
+    },
+    Migration {
+        version: 3,
+        name: "categories",
+        sql: include_str!("../../sql/migrations/0003_categories.sql"),
+        pre_migrate: None,
+        post_migrate: Some(backfill_paper_categories),
+    },
+    Migration {
+        version: 4,
+        name: "version_timestamps",
+        sql: include_str!("../../sql/migrations/0004_version_timestamps.sql"),
+        pre_migrate: None,
+        post_migrate: Some(backfill_version_timestamps),
+    },
+    Migration {
+        version: 5,
+        name: "lookup_indexes",
+        sql: include_str!("../../sql/migrations/0005_lookup_indexes.sql"),
+        pre_migrate: None,
+        post_migrate: None,
+    },
+    Migration {
+        version: 6,
+        name: "paper_keywords",
+        sql: include_str!("../../sql/migrations/0006_paper_keywords.sql"),
+        pre_migrate: None,
+        post_migrate: Some(split_keyword_strings),
+    },
+    Migration {
+        version: 7,
+        name: "drop_keyword_strings",
+        sql: include_str!("../../sql/migrations/0007_drop_keyword_strings.sql"),
+        pre_migrate: None,
+        post_migrate: None,
+    },
+    Migration {
+        version: 8,
+        name: "extraction_provenance",
+        sql: include_str!("../../sql/migrations/0008_extraction_provenance.sql"),
+        pre_migrate: None,
+        post_migrate: None,
+    },
+    Migration {
+        version: 9,
+        name: "paper_search",
+        sql: include_str!("../../sql/migrations/0009_paper_search.sql"),
+        pre_migrate: None,
+        post_migrate: None,
+    },
+    Migration {
+        version: 10,
+        name: "paper_text",
+        sql: include_str!("../../sql/migrations/0010_paper_text.sql"),
+        pre_migrate: None,
+        post_migrate: None,
+    },
+    Migration {
+        version: 11,
+        name: "extraction_attempts",
+        sql: include_str!("../../sql/migrations/0011_extraction_attempts.sql"),
+        pre_migrate: None,
+        post_migrate: None,
+    },
+    Migration {
+        version: 12,
+        name: "unique_paper_data",
+        sql: include_str!("../../sql/migrations/0012_unique_paper_data.sql"),
+        pre_migrate: Some(require_unique_paper_data),
+        post_migrate: None,
+    },
+];
+
+/// the schema version this binary writes
+pub const LATEST_SCHEMA_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+/// where a database stands relative to [MIGRATIONS]
+#[derive(Debug, Clone, Copy)]
+pub struct SchemaStatus {
+    /// value of `PRAGMA user_version`
+    pub user_version: u32,
+    /// for databases created before versioned migrations, the version their schema matches. These
+    /// are stamped with it before any migration runs.
+    pub legacy_version: Option<u32>,
+}
+
+impl SchemaStatus {
+    pub fn read(conn: &Connection) -> AppResult<Self> {
+        let user_version = conn.pragma_query_value(None, "user_version", |row| row.get::<_, u32>(0))?;
+        let legacy_version = if user_version == 0 {
+            detect_legacy_version(conn)?
+        } else {
+            None
+        };
+
+        Ok(Self {
+            user_version,
+            legacy_version,
+        })
+    }
+
+    /// the schema version the database effectively has
+    pub fn version(&self) -> u32 {
+        self.legacy_version.unwrap_or(self.user_version)
+    }
+
+    /// whether the database can be used without migrating, which unversioned ones never can
+    pub fn is_current(&self) -> bool {
+        self.is_at_least(LATEST_SCHEMA_VERSION)
+    }
+
+    /// whether every migration up to `version` has been applied
+    pub fn is_at_least(&self, version: u32) -> bool {
+        self.legacy_version.is_none() && self.user_version >= version
+    }
+
+    pub fn is_newer_than_supported(&self) -> bool {
+        self.user_version > LATEST_SCHEMA_VERSION
+    }
+
+    pub fn pending(&self) -> impl Iterator<Item=&'static Migration> {
+        let version = self.version();
+        MIGRATIONS.iter()
+            .filter(move |migration| migration.version > version)
+    }
+}
+
+/// applies every pending migration up to and including `target_version`, and returns the ones
+/// that ran
+pub(crate) fn run_migrations(conn: &mut Connection, target_version: u32) -> AppResult<Vec<&'static Migration>> {
+    let status = SchemaStatus::read(conn)?;
+    if let Some(legacy_version) = status.legacy_version {
+        info!("adopting unversioned database at schema version {}", legacy_version);
+        conn.pragma_update(None, "user_version", legacy_version)?;
+    }
+
+    let mut applied = Vec::new();
+    for migration in status.pending().take_while(|migration| migration.version <= target_version) {
+        let txn = conn.transaction()?;
+        if let Some(pre_migrate) = migration.pre_migrate {
+            if let Some(reason) = pre_migrate(&ArxivDBQueries::wrap(&txn))? {
+                return Err(AppError::MigrationBlocked {
+                    migration: migration.to_string(),
+                    reason,
+                });
+            }
+        }
+        txn.execute_batch(migration.sql)?;
+        if let Some(post_migrate) = migration.post_migrate {
+            post_migrate(&ArxivDBQueries::wrap(&txn))?;
+        }
+        txn.pragma_update(None, "user_version", migration.version)?;
+        txn.commit()?;
+
+        info!("applied migration {}", migration);
+        applied.push(migration);
+    }
+
+    Ok(applied)
+}
+
+/// Works out which version an unversioned database corresponds to. Before migrations were
+/// versioned, the whole schema was re-run with `CREATE ... IF NOT EXISTS` on every open, so a
+/// database either has the baseline tables only or everything up to the typed timestamp columns.
+fn detect_legacy_version(conn: &Connection) -> AppResult<Option<u32>> {
+    if !column_exists(conn, "arxiv_metadata", "id")? {
+        return Ok(None);
+    }
+
+    if column_exists(conn, "arxiv_version", "created_utc")? {
+        Ok(Some(4))
+    } else {
+        Ok(Some(1))
+    }
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> AppResult<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM pragma_table_info(:table) WHERE name = :column)",
+        named_params! { ":table": table, ":column": column },
+        |row| row.get::<_, bool>(0)
+    ).map_err(Into::into)
+}
+
+fn backfill_paper_categories(queries: &ArxivDBQueries) -> AppResult<()> {
+    let backfilled = queries.backfill_paper_categories()?;
+    info!("backfilled {} paper categories", backfilled);
+    Ok(())
+}
+
+fn backfill_version_timestamps(queries: &ArxivDBQueries) -> AppResult<()> {
+    let backfilled = queries.backfill_version_timestamps()?;
+    info!("backfilled {} version creation timestamp(s)", backfilled);
+    Ok(())
+}
+
+/// Duplicate rows would make the unique index fail halfway. Deleting them here would lose them
+/// without a report, so leave that to `db check --repair`.
+fn require_unique_paper_data(queries: &ArxivDBQueries) -> AppResult<Option<String>> {
+    let duplicates = queries.conn.query_row(
+        "SELECT COUNT(*) - COUNT(DISTINCT arxiv_id) FROM paper_data",
+        [],
+        |row| row.get::<_, u64>(0)
+    )?;
+
+    Ok((duplicates > 0).then(|| format!(
+        "{} duplicate paper_data row(s); run `db check --repair` to remove them first",
+        duplicates
+    )))
+}
+
+/// moves the comma-joined `paper_data.keywords` strings into `paper_keyword`. Keywords that
+/// contained commas were already split apart when they were saved, so this is as good as it gets.
+fn split_keyword_strings(queries: &ArxivDBQueries) -> AppResult<()> {
+    let mut stmt = queries.conn.prepare("SELECT arxiv_id, keywords FROM paper_data WHERE LENGTH(keywords) > 0")?;
+    let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (arxiv_id, keywords) in &rows {
+        let keywords = keywords.split(',')
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        queries.replace_paper_keywords(arxiv_id, &keywords)?;
+    }
+
+    info!("split {} comma-joined keyword list(s) into rows", rows.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::pages::QueryPage;
+    use crate::db::ArxivDB;
+
+    fn user_version(conn: &Connection) -> u32 {
+        conn.pragma_query_value(None, "user_version", |row| row.get::<_, u32>(0)).unwrap()
+    }
+
+    fn applied_versions(applied: &[&Migration]) -> Vec<u32> {
+        applied.iter().map(|migration| migration.version).collect()
+    }
+
+    /// a database as the unversioned binaries left it, with the scripts up to `version` applied
+    fn legacy_db(version: u32) -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for migration in MIGRATIONS.iter().take_while(|migration| migration.version <= version) {
+            conn.execute_batch(migration.sql).unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn migrates_fresh_db_to_latest() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let status = SchemaStatus::read(&conn).unwrap();
+        assert_eq!((status.user_version, status.legacy_version), (0, None));
+
+        let applied = run_migrations(&mut conn, LATEST_SCHEMA_VERSION).unwrap();
+        assert_eq!(applied_versions(&applied), (1..=LATEST_SCHEMA_VERSION).collect::<Vec<_>>());
+        assert_eq!(user_version(&conn), LATEST_SCHEMA_VERSION);
+        assert!(SchemaStatus::read(&conn).unwrap().is_current());
+
+        // nothing left to do the second time
+        assert!(run_migrations(&mut conn, LATEST_SCHEMA_VERSION).unwrap().is_empty());
+    }
+
+    #[test]
+    fn adopts_legacy_db() {
+        for legacy_version in [1, 4] {
+            let mut conn = legacy_db(legacy_version);
+            let status = SchemaStatus::read(&conn).unwrap();
+            assert_eq!(status.legacy_version, Some(legacy_version));
+            assert!(!status.is_current());
+
+            let applied = run_migrations(&mut conn, LATEST_SCHEMA_VERSION).unwrap();
+            assert_eq!(applied_versions(&applied), (legacy_version + 1..=LATEST_SCHEMA_VERSION).collect::<Vec<_>>());
+            assert_eq!(user_version(&conn), LATEST_SCHEMA_VERSION);
+        }
+    }
+
+    #[test]
+    fn stops_at_target_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn, 5).unwrap();
+        let status = SchemaStatus::read(&conn).unwrap();
+        assert_eq!(status.version(), 5);
+        assert_eq!(status.pending().next().map(|migration| migration.version), Some(6));
+    }
+
+    #[test]
+    fn splits_keyword_strings_into_rows() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn, 5).unwrap();
+        conn.execute_batch(r"
+            INSERT INTO arxiv_metadata (id) VALUES ('2101.00001'), ('2101.00002');
+            INSERT INTO paper_data (arxiv_id, abstract, keywords, content) VALUES
+                ('2101.00001', 'An abstract.', 'Graph  Networks, sparsity,,deep learning', 'A body.'),
+                ('2101.00002', 'An abstract.', '', 'A body.');
+            INSERT INTO extraction_result (arxiv_id, status_code) VALUES ('2101.00001', 'OK'), ('2101.00002', 'OK');
+        ").unwrap();
+
+        run_migrations(&mut conn, LATEST_SCHEMA_VERSION).unwrap();
+        assert!(!column_exists(&conn, "paper_data", "keywords").unwrap());
+
+        let mut stmt = conn.prepare("SELECT arxiv_id, position, keyword, normalized FROM paper_keyword ORDER BY arxiv_id, position").unwrap();
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?)))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        // trimmed, blanks dropped and positions kept contiguous
+        let expected = [
+            (0, "Graph  Networks", "graph networks"),
+            (1, "sparsity", "sparsity"),
+            (2, "deep learning", "deep learning"),
+        ].map(|(position, keyword, normalized)| ("2101.00001".to_string(), position, keyword.to_string(), normalized.to_string()));
+        assert_eq!(rows, expected);
+
+        let keywords_json = conn.query_row("SELECT keywords FROM training_data", [], |row| row.get::<_, String>(0)).unwrap();
+        assert_eq!(keywords_json, r#"["Graph  Networks","sparsity","deep learning"]"#);
+
+        // papers without keywords drop out of the training data, as they did before
+        let records = ArxivDBQueries::wrap(&conn).select_training_data(&QueryPage::first(10), None).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, "2101.00001");
+        assert_eq!(records[0].keywords, vec!["Graph  Networks", "sparsity", "deep learning"]);
+    }
+
+    #[test]
+    fn refuses_newer_schema() {
+        // a shared in-memory database, so the schema stamped here is what ArxivDB opens
+        let uri = "file:refuses_newer_schema?mode=memory&cache=shared";
+        let conn = Connection::open(uri).unwrap();
+        conn.pragma_update(None, "user_version", LATEST_SCHEMA_VERSION + 1).unwrap();
+        assert!(SchemaStatus::read(&conn).unwrap().is_newer_than_supported());
+
+        match ArxivDB::open(uri) {
+            Err(AppError::SchemaTooNew { found, supported }) => {
+                assert_eq!((found, supported), (LATEST_SCHEMA_VERSION + 1, LATEST_SCHEMA_VERSION));
+            }
+            Err(err) => panic!("unexpected error: {}", err),
+            Ok(_) => panic!("opened a database newer than supported"),
+        }
+    }
+}
@@ -1,35 +1,93 @@
 use std::fmt::{Display, Formatter};
+use crate::err::AppResult;
 
-#[derive(Debug)]
+/// A page of a keyset scan: up to `limit` rows whose key sorts strictly after `after`. Unlike an
+/// offset, the key keeps later pages cheap and stable while rows are being written.
+#[derive(Debug, Clone)]
 pub struct QueryPage {
-    pub offset: u64,
+    /// key of the last row of the previous page. Empty for the first page, since every key sorts
+    /// after the empty string.
+    pub after: String,
     pub limit: u64,
 }
 
 impl Default for QueryPage {
     fn default() -> Self {
+        Self::first(100)
+    }
+}
+
+impl QueryPage {
+    pub fn first(limit: u64) -> Self {
         Self {
-            offset: 0,
-            limit: 100
+            after: String::new(),
+            limit,
+        }
+    }
+
+    /// the page following one whose last key was `last_key`
+    pub fn next(&self, last_key: String) -> Self {
+        Self {
+            after: last_key,
+            limit: self.limit,
         }
     }
 }
 
 impl Display for QueryPage {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "({}:{})", self.offset, self.limit)
+        write!(f, "({}..:{})", self.after, self.limit)
     }
 }
 
-/// creates an iteration of pages to pull the given number of elements
-pub fn page_iter(total: u64, page_size: u64) -> impl Iterator<Item=QueryPage> {
-    let page_count = total.div_ceil(page_size);
-    (0..page_count).into_iter()
-        .map(move |page_idx| {
-            let offset = page_idx * page_size;
-            QueryPage {
-                limit: page_size,
-                offset,
-            }
-        })
+/// Streams a keyset-paginated query one page at a time, until a page comes back short. The total
+/// row count doesn't need to be known ahead of time.
+pub struct PageCursor<T, FetchT, KeyT> {
+    next_page: Option<QueryPage>,
+    fetch: FetchT,
+    key: KeyT,
+    _row: std::marker::PhantomData<T>,
+}
+
+impl<T, FetchT, KeyT> PageCursor<T, FetchT, KeyT>
+where
+    FetchT: FnMut(&QueryPage) -> AppResult<Vec<T>>,
+    KeyT: Fn(&T) -> String,
+{
+    /// `fetch` loads a page, and `key` returns the key a row is ordered by
+    pub fn new(page_size: u64, fetch: FetchT, key: KeyT) -> Self {
+        Self {
+            next_page: Some(QueryPage::first(page_size.max(1))),
+            fetch,
+            key,
+            _row: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<T, FetchT, KeyT> Iterator for PageCursor<T, FetchT, KeyT>
+where
+    FetchT: FnMut(&QueryPage) -> AppResult<Vec<T>>,
+    KeyT: Fn(&T) -> String,
+{
+    type Item = AppResult<Vec<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page = self.next_page.take()?;
+        let rows = match (self.fetch)(&page) {
+            Ok(rows) => rows,
+            Err(err) => return Some(Err(err)),
+        };
+
+        if rows.is_empty() {
+            return None;
+        }
+
+        if rows.len() as u64 == page.limit {
+            let last_key = (self.key)(rows.last().expect("page is not empty"));
+            self.next_page = Some(page.next(last_key));
+        }
+
+        Some(Ok(rows))
+    }
 }
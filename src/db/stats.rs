@@ -0,0 +1,144 @@
+use rusqlite::Row;
+use serde::Serialize;
+use crate::db::ArxivDBQueries;
+use crate::err::AppResult;
+
+/// overview of how far the dataset has come, as shown by `db stats`
+#[derive(Debug, Clone, Serialize)]
+pub struct DatasetStats {
+    pub papers: u64,
+    /// papers with an extraction result, successful or not
+    pub processed: u64,
+    pub unprocessed: u64,
+    /// extraction results per status code, largest first
+    pub statuses: Vec<StatusCount>,
+    /// rows in the `training_data` view
+    pub training_rows: u64,
+    /// how many papers with an extracted body have each number of keywords
+    pub keyword_counts: Vec<KeywordCountBucket>,
+    pub average_keywords: Option<f64>,
+    /// mean length of non-empty extracted bodies, in characters
+    pub average_body_chars: Option<f64>,
+    pub by_primary_category: Vec<StatsBreakdown>,
+    /// by the year of the first version
+    pub by_year: Vec<StatsBreakdown>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusCount {
+    pub status_code: String,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeywordCountBucket {
+    pub keywords: u64,
+    pub papers: u64,
+}
+
+/// paper counts for one category or year. `key` is `unknown` for papers without one.
+#[derive(Debug, Clone, Serialize)]
+pub struct StatsBreakdown {
+    pub key: String,
+    pub papers: u64,
+    pub processed: u64,
+    pub training_rows: u64,
+}
+
+impl ArxivDBQueries<'_> {
+    pub fn dataset_stats(&self) -> AppResult<DatasetStats> {
+        let papers = self.count_arxiv_ids()?;
+        let processed = self.conn.query_row(
+            "SELECT COUNT(*) FROM arxiv_metadata WHERE EXISTS (SELECT 1 FROM extraction_result WHERE extraction_result.arxiv_id = arxiv_metadata.id)",
+            [],
+            |row| row.get::<_, u64>(0)
+        )?;
+
+        let statuses = self.conn.prepare(r"
+        SELECT COALESCE(status_code, 'NONE') AS status_code, COUNT(*) AS count
+        FROM extraction_result
+        GROUP BY status_code
+        ORDER BY count DESC, status_code
+        ")?
+            .query_map([], |row| Ok(StatusCount {
+                status_code: row.get("status_code")?,
+                count: row.get("count")?,
+            }))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let keyword_counts = self.conn.prepare(r"
+        SELECT keywords, COUNT(*) AS papers
+        FROM (
+            SELECT (SELECT COUNT(*) FROM paper_keyword WHERE paper_keyword.arxiv_id = paper_data.arxiv_id) AS keywords
+            FROM paper_data
+            WHERE LENGTH(paper_data.content) > 0
+        )
+        GROUP BY keywords
+        ORDER BY keywords
+        ")?
+            .query_map([], |row| Ok(KeywordCountBucket {
+                keywords: row.get("keywords")?,
+                papers: row.get("papers")?,
+            }))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let extracted = keyword_counts.iter().map(|bucket| bucket.papers).sum::<u64>();
+        let average_keywords = (extracted > 0).then(|| {
+            let keywords = keyword_counts.iter().map(|bucket| bucket.keywords * bucket.papers).sum::<u64>();
+            keywords as f64 / extracted as f64
+        });
+
+        let average_body_chars = self.conn.query_row(
+            "SELECT AVG(LENGTH(content)) FROM paper_data WHERE LENGTH(content) > 0",
+            [],
+            |row| row.get::<_, Option<f64>>(0)
+        )?;
+
+        Ok(DatasetStats {
+            papers,
+            processed,
+            unprocessed: papers.saturating_sub(processed),
+            statuses,
+            training_rows: self.count_training_data(None)?,
+            keyword_counts,
+            average_keywords,
+            average_body_chars,
+            by_primary_category: self.stats_breakdown(
+                "(SELECT category FROM paper_category WHERE paper_category.arxiv_id = arxiv_metadata.id AND is_primary)"
+            )?,
+            by_year: self.stats_breakdown(
+                "(SELECT SUBSTR(MIN(created_utc), 1, 4) FROM arxiv_version WHERE arxiv_version.arxiv_id = arxiv_metadata.id)"
+            )?,
+        })
+    }
+
+    /// groups papers by `key_expr`, an expression over `arxiv_metadata`
+    fn stats_breakdown(&self, key_expr: &str) -> AppResult<Vec<StatsBreakdown>> {
+        let query = format!(r"
+        SELECT COALESCE(key, 'unknown') AS key, COUNT(*) AS papers, SUM(processed) AS processed, SUM(training) AS training_rows
+        FROM (
+            SELECT
+                {} AS key,
+                EXISTS (SELECT 1 FROM extraction_result WHERE extraction_result.arxiv_id = arxiv_metadata.id) AS processed,
+                EXISTS (SELECT 1 FROM training_data WHERE training_data.arxiv_id = arxiv_metadata.id) AS training
+            FROM arxiv_metadata
+        )
+        GROUP BY key
+        ORDER BY key
+        ", key_expr);
+
+        let rows = self.conn.prepare(&query)?
+            .query_map([], breakdown_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+}
+
+fn breakdown_from_row(row: &Row) -> rusqlite::Result<StatsBreakdown> {
+    Ok(StatsBreakdown {
+        key: row.get("key")?,
+        papers: row.get("papers")?,
+        processed: row.get("processed")?,
+        training_rows: row.get("training_rows")?,
+    })
+}
@@ -24,6 +24,44 @@ pub enum AppError {
     HttpStatusError(ureq::http::status::StatusCode),
     #[error("No GCS bucket object for arxiv id {0}")]
     NoBucketObject(String),
+    #[error("No PDF for arxiv id {arxiv_id} in {location}")]
+    PdfNotFound {
+        arxiv_id: String,
+        location: String,
+    },
+    #[error("Invalid metadata record on line {line} (byte offset {offset}): {source}")]
+    InvalidMetadataRecord {
+        line: u64,
+        offset: u64,
+        raw: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Invalid arxiv id '{0}'")]
+    InvalidArxivId(String),
+    #[error("Database schema version {found} is newer than this build supports ({supported})")]
+    SchemaTooNew {
+        found: u32,
+        supported: u32,
+    },
+    #[error("Database schema version {found} is behind this build ({expected}); run `db migrate` first")]
+    SchemaOutdated {
+        found: u32,
+        expected: u32,
+    },
+    #[error("Migration {migration} can't be applied: {reason}")]
+    MigrationBlocked {
+        migration: String,
+        reason: String,
+    },
+    #[error("Checksum mismatch for {object}: expected MD5 {expected}, got {found}")]
+    ChecksumMismatch {
+        object: String,
+        expected: String,
+        found: String,
+    },
+    #[error("Extraction panicked: {0}")]
+    Panic(String),
     #[error("Other error: {0}")]
     Other(String),
 }
@@ -43,6 +81,14 @@ impl AppError {
             AppError::NetworkError(_) => "NETWORK",
             AppError::HttpStatusError(_) => "HTTP_STAT",
             AppError::NoBucketObject(_) => "NO_GCS_OBJ",
+            AppError::PdfNotFound { .. } => "NO_LOCAL_PDF",
+            AppError::InvalidMetadataRecord { .. } => "INVALID_METADATA",
+            AppError::InvalidArxivId(_) => "INVALID_ID",
+            AppError::SchemaTooNew { .. } => "SCHEMA_VERSION",
+            AppError::SchemaOutdated { .. } => "SCHEMA_VERSION",
+            AppError::MigrationBlocked { .. } => "SCHEMA_VERSION",
+            AppError::ChecksumMismatch { .. } => "CHECKSUM",
+            AppError::Panic(_) => "PANIC",
             AppError::Other(_) => "OTHER"
         }
     }
@@ -1,9 +1,20 @@
+pub mod pipeline;
+
+use std::time::Duration;
+use chrono::Utc;
 use log::debug;
-use crate::content::ArxivPaperContent;
+use crate::cache::PdfCache;
+use crate::content::{ArxivPaperContent, ExtractionProvenance, PaperText};
 use crate::content::body::PaperBodyExtractor;
 use crate::content::keyword::KeywordExtractor;
 use crate::err::AppError;
+use crate::fetch::source::PaperSource;
 use crate::fetch::PaperDownloader;
+use crate::metadata::id::ArxivId;
+use crate::metadata::to_iso8601_utc;
+
+/// recorded with every extraction. Bump the crate version when extraction logic changes.
+pub const EXTRACTOR_VERSION: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
 pub struct ContentExtractor {
     keyword_extractor: KeywordExtractor,
@@ -11,6 +22,12 @@ pub struct ContentExtractor {
     paper_downloader: PaperDownloader,
 }
 
+impl Default for ContentExtractor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ContentExtractor {
     pub fn new() -> Self {
         Self {
@@ -20,25 +37,52 @@ impl ContentExtractor {
         }
     }
 
+    /// read PDFs from somewhere other than the public GCS bucket
+    pub fn with_paper_source(mut self, source: Box<dyn PaperSource>) -> Self {
+        self.paper_downloader = self.paper_downloader.with_source(source);
+        self
+    }
+
+    /// serve PDFs from a local cache where possible
+    pub fn with_pdf_cache(mut self, cache: PdfCache) -> Self {
+        self.paper_downloader = self.paper_downloader.with_cache(cache);
+        self
+    }
+
     /// actually performs the relevant steps to fetch a paper and pull out content we want
-    pub fn fetch_and_extract_content(&self, arxiv_id: String) -> ExtractResult<ArxivPaperContent> {
-        // get the paper content
-        let content = self.paper_downloader.fetch_paper_content(&arxiv_id)
-            .map_err(error_mapper(&arxiv_id))?;
-        debug!("processing {}: fetched content", arxiv_id);
+    pub fn fetch_and_extract_content(&self, arxiv_id: ArxivId) -> ExtractResult<ArxivPaperContent> {
+        let text = self.fetch_text(&arxiv_id)?;
+        self.extract_from_text(&text)
+    }
+
+    /// downloads a paper's PDF and converts it to text
+    pub fn fetch_text(&self, arxiv_id: &ArxivId) -> ExtractResult<PaperText> {
+        let text = self.paper_downloader.fetch_paper_content(arxiv_id)
+            .map_err(error_mapper(&arxiv_id.base_id()))?;
+        debug!("processing {}: fetched content from {}", arxiv_id, text.source_object.as_deref().unwrap_or("?"));
+        Ok(text)
+    }
 
-        self.extract_content(arxiv_id, &content)
+    /// runs the keyword and body stages over a paper's text, crediting its source in the provenance
+    pub fn extract_from_text(&self, text: &PaperText) -> ExtractResult<ArxivPaperContent> {
+        let mut content = self.extract_content(text.arxiv_id.clone(), &text.text)?;
+        if let Some(provenance) = content.provenance.as_mut() {
+            provenance.source_object = text.source_object.clone();
+            provenance.source_generation = text.source_generation;
+        }
+
+        Ok(content)
     }
     
     pub fn extract_content<StrT: Into<String>>(&self, arxiv_id: StrT, content: &str) -> ExtractResult<ArxivPaperContent> {
         let arxiv_id = arxiv_id.into();
         // get the keywords
-        let keywords = self.keyword_extractor.extract_keywords(&content)
+        let (keyword_strategy, keywords) = self.keyword_extractor.extract_keywords_with_strategy(content)
             .map_err(error_mapper(&arxiv_id))?;
-        debug!("processing {}: extracted keywords", arxiv_id);
+        debug!("processing {}: extracted keywords using {} strategy", arxiv_id, keyword_strategy);
 
         // extract the paper content
-        let content = self.paper_body_extractor.extract_body(&content)
+        let (content, body_boundaries) = self.paper_body_extractor.extract_body_with_boundaries(content)
             .map_err(error_mapper(&arxiv_id))?;
         debug!("processing {}: extracted paper body", arxiv_id);
 
@@ -47,6 +91,14 @@ impl ContentExtractor {
             paper_content: content,
             abstract_text: String::new(),
             keywords,
+            provenance: Some(ExtractionProvenance {
+                keyword_strategy,
+                body_boundaries,
+                extractor_version: EXTRACTOR_VERSION,
+                extracted_utc: to_iso8601_utc(&Utc::now()),
+                source_object: None,
+                source_generation: None,
+            }),
         })
     }
 }
@@ -85,6 +137,8 @@ pub struct ExtractResultRecord {
     pub arxiv_id: String,
     pub extract_status: String,
     pub extract_msg: Option<String>,
+    /// how long the attempt took, if it was timed
+    pub duration: Option<Duration>,
 }
 
 impl From<ExtractError> for ExtractResultRecord {
@@ -93,6 +147,7 @@ impl From<ExtractError> for ExtractResultRecord {
             arxiv_id: value.arxiv_id,
             extract_status: value.err.extraction_status_code().to_string(),
             extract_msg: Some(value.err.to_string()),
+            duration: None,
         }
     }
 }
@@ -103,6 +158,16 @@ impl ExtractResultRecord {
             arxiv_id: id.into(),
             extract_status: "OK".to_string(),
             extract_msg: None,
+            duration: None,
         }
     }
+
+    /// builds the record of an attempt that took `duration`
+    pub fn attempt(id: &str, err: Option<ExtractError>, duration: Duration) -> Self {
+        let mut record = err
+            .map(Self::from)
+            .unwrap_or(Self::success(id));
+        record.duration = Some(duration);
+        record
+    }
 }
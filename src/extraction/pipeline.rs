@@ -0,0 +1,332 @@
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use log::{debug, error, info};
+use threadpool::ThreadPool;
+use crate::content::{ArxivPaperContent, PaperText};
+use crate::err::{AppError, AppResult};
+use crate::extraction::{ContentExtractor, ExtractError, ExtractResult};
+use crate::metadata::id::ArxivId;
+use crate::storage::PaperStore;
+
+/// what a worker produced for one paper. Downloaded text is kept even when extraction fails, so a
+/// later `--from-cache` run can retry it without downloading the PDF again.
+pub struct ExtractOutcome {
+    /// text that was downloaded for this attempt, and isn't stored yet
+    pub text: Option<PaperText>,
+    pub result: ExtractResult<ArxivPaperContent>,
+    pub duration: Duration,
+}
+
+/// Runs a [ContentExtractor] over pages of papers on a thread pool, and saves the outcomes of each
+/// page to a [PaperStore] in one batch. Works with any store, so pipelines can run without a
+/// database file.
+pub struct ExtractionPipeline {
+    extractor: Arc<ContentExtractor>,
+    pool: ThreadPool,
+}
+
+impl ExtractionPipeline {
+    pub fn new(extractor: ContentExtractor, pool: ThreadPool) -> Self {
+        Self {
+            extractor: Arc::new(extractor),
+            pool,
+        }
+    }
+
+    /// Downloads and extracts each page of ids, then saves the results of the page in one batch.
+    /// Every mode that works from ids goes through here, so they all record status the same way.
+    pub fn process_pages<PagesT>(&self, store: &impl PaperStore, pages: PagesT) -> AppResult<()>
+    where
+        PagesT: Iterator<Item=AppResult<Vec<ArxivId>>>,
+    {
+        for ids in pages {
+            let ids = match ids {
+                Ok(ids) => {
+                    info!("processing page of {} id(s) starting at {}", ids.len(), ids[0]);
+                    ids
+                }
+                Err(err) => {
+                    error!("error encountered while fetching ids from db: {}", err);
+                    return Err(err);
+                }
+            };
+
+            let outcomes = self.extract_papers(ids);
+            save_outcomes(store, outcomes)?;
+        }
+
+        Ok(())
+    }
+
+    /// re-extracts every paper with stored text, replacing its content and recording a new attempt
+    pub fn process_cached(&self, store: &impl PaperStore, page_size: u64) -> AppResult<()> {
+        for ids in store.arxiv_id_with_text_pages(page_size) {
+            let ids = ids?;
+            info!("re-extracting page of {} id(s) starting at {}", ids.len(), ids[0]);
+
+            let mut texts = Vec::with_capacity(ids.len());
+            for id in &ids {
+                if let Some(text) = store.select_paper_text(&id.base_id())? {
+                    texts.push(text);
+                }
+            }
+
+            let outcomes = self.extract_texts(texts);
+            save_outcomes(store, outcomes)?;
+        }
+
+        Ok(())
+    }
+
+    /// fetches and extracts papers in parallel, without saving anything
+    pub fn extract_papers(&self, ids: Vec<ArxivId>) -> Vec<ExtractOutcome> {
+        let (sender, recv) = std::sync::mpsc::channel::<ExtractOutcome>();
+
+        for id in ids {
+            let extractor = self.extractor.clone();
+            let send = sender.clone();
+            self.pool.execute(move || {
+                debug!("starting to extract content for {}", id);
+                let started = Instant::now();
+                let outcome = catch_panic(&id.base_id(), started, || match extractor.fetch_text(&id) {
+                    Ok(text) => ExtractOutcome {
+                        result: extractor.extract_from_text(&text),
+                        text: Some(text),
+                        duration: started.elapsed(),
+                    },
+                    Err(err) => ExtractOutcome {
+                        text: None,
+                        result: Err(err),
+                        duration: started.elapsed(),
+                    },
+                });
+                send.send(outcome).unwrap();
+            })
+        }
+
+        drop(sender);
+
+        self.pool.join();
+
+        recv.iter().collect()
+    }
+
+    /// runs the keyword and body stages over stored text in parallel, without saving anything
+    pub fn extract_texts(&self, texts: Vec<PaperText>) -> Vec<ExtractOutcome> {
+        let (sender, recv) = std::sync::mpsc::channel::<ExtractOutcome>();
+
+        for text in texts {
+            let extractor = self.extractor.clone();
+            let send = sender.clone();
+            self.pool.execute(move || {
+                debug!("starting to re-extract content for {}", text.arxiv_id);
+                let started = Instant::now();
+                let outcome = catch_panic(&text.arxiv_id, started, || ExtractOutcome {
+                    text: None,
+                    result: extractor.extract_from_text(&text),
+                    duration: started.elapsed(),
+                });
+                send.send(outcome).unwrap();
+            })
+        }
+
+        drop(sender);
+
+        self.pool.join();
+
+        recv.iter().collect()
+    }
+}
+
+/// Runs a worker's job, turning a panic into a failed outcome. `pdf_extract` panics on some
+/// malformed PDFs, and without an attempt on record such papers would stay pending forever.
+fn catch_panic<JobT>(arxiv_id: &str, started: Instant, job: JobT) -> ExtractOutcome
+where
+    JobT: FnOnce() -> ExtractOutcome,
+{
+    std::panic::catch_unwind(AssertUnwindSafe(job))
+        .unwrap_or_else(|panic| {
+            let message = panic.downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+
+            ExtractOutcome {
+                text: None,
+                result: Err(ExtractError {
+                    arxiv_id: arxiv_id.to_string(),
+                    err: AppError::Panic(message),
+                }),
+                duration: started.elapsed(),
+            }
+        })
+}
+
+/// writes a round of outcomes in one batch: newly fetched text, content, and an extraction
+/// attempt per paper
+pub fn save_outcomes(store: &impl PaperStore, outcomes: Vec<ExtractOutcome>) -> AppResult<()> {
+    store.batch(|store| {
+        for outcome in outcomes {
+            if let Some(text) = &outcome.text {
+                store.insert_paper_text(text)?;
+            }
+
+            match outcome.result {
+                Ok(content) => {
+                    info!("inserting content for {}", &content.id);
+                    // update the status
+                    store.insert_extraction_result(&content.id, None, outcome.duration)?;
+                    // insert the content
+                    store.upsert_content(content)?;
+                }
+                Err(err) => {
+                    error!(
+                        "error while extracting content from {}: {}",
+                        err.id(),
+                        err.app_err()
+                    );
+                    // just log that we had some kind of error
+                    store.insert_extraction_result("", Some(err), outcome.duration)?;
+                }
+            }
+        }
+
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::body::BodyBoundaries;
+    use crate::content::keyword::KeywordStrategy;
+    use crate::content::ExtractionProvenance;
+    use crate::extraction::EXTRACTOR_VERSION;
+    use crate::fetch::local::FlatDirSource;
+    use crate::fetch::source::{PaperSource, PdfLocation};
+    use crate::metadata::ArxivMetadata;
+    use crate::storage::memory::MemoryPaperStore;
+
+    fn paper(id: &str) -> ArxivMetadata {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "abstract": "An abstract.",
+            "categories": "cs.LG",
+            "versions": [{ "version": "v1", "created": "Mon, 4 Jan 2021 10:00:00 GMT" }],
+        })).unwrap()
+    }
+
+    fn pipeline() -> ExtractionPipeline {
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/pdf");
+        let extractor = ContentExtractor::new()
+            .with_paper_source(Box::new(FlatDirSource::new(fixtures)));
+        ExtractionPipeline::new(extractor, ThreadPool::new(2))
+    }
+
+    #[test]
+    fn extracts_into_memory_store() {
+        let store = MemoryPaperStore::new();
+        // 2101.00002 has a PDF in the fixtures, 2101.00003 doesn't
+        store.insert_arxiv_metadata(paper("2101.00002")).unwrap();
+        store.insert_arxiv_metadata(paper("2101.00003")).unwrap();
+
+        let pipeline = pipeline();
+        pipeline.process_pages(&store, store.arxiv_id_pages(1)).unwrap();
+
+        assert_eq!(store.extraction_status("2101.00002").as_deref(), Some("OK"));
+        assert_eq!(store.extraction_status("2101.00003").as_deref(), Some("NO_LOCAL_PDF"));
+        assert_eq!(store.count_papers_with_text().unwrap(), 1);
+
+        let records = store.training_data_pages(10, None)
+            .collect::<AppResult<Vec<_>>>()
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, "2101.00002");
+        assert_eq!(records[0].abstract_text, "An abstract.");
+        assert_eq!(records[0].keywords, vec!["flat source".to_string()]);
+        assert!(records[0].paper_content.contains("Flat body."));
+
+        // re-extracting from the stored text records another attempt without downloading
+        pipeline.process_cached(&store, 10).unwrap();
+        assert_eq!(store.extraction_attempts("2101.00002"), 2);
+        assert_eq!(store.count_training_data(None).unwrap(), 1);
+    }
+
+    fn content(id: &str, strategy: KeywordStrategy, extractor_version: &'static str) -> ArxivPaperContent {
+        ArxivPaperContent {
+            id: id.to_string(),
+            abstract_text: "An abstract.".to_string(),
+            keywords: vec!["old".to_string()],
+            paper_content: "Old body.".to_string(),
+            provenance: Some(ExtractionProvenance {
+                keyword_strategy: strategy,
+                body_boundaries: BodyBoundaries {
+                    start_pattern: String::new(),
+                    end_pattern: String::new(),
+                },
+                extractor_version,
+                extracted_utc: "2021-01-04T10:00:00Z".to_string(),
+                source_object: None,
+                source_generation: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn reextracts_outdated_strategy() {
+        let store = MemoryPaperStore::new();
+        store.insert_arxiv_metadata(paper("2101.00002")).unwrap();
+        store.insert_arxiv_metadata(paper("2101.00003")).unwrap();
+        store.insert_arxiv_metadata(paper("2101.00004")).unwrap();
+        store.upsert_content(content("2101.00002", KeywordStrategy::SmallLabel, "keyword-dataset-rs/0.0.1")).unwrap();
+        store.upsert_content(content("2101.00003", KeywordStrategy::SmallLabel, EXTRACTOR_VERSION)).unwrap();
+        store.upsert_content(content("2101.00004", KeywordStrategy::Headers, "keyword-dataset-rs/0.0.1")).unwrap();
+
+        let outdated = Some(EXTRACTOR_VERSION);
+        assert_eq!(store.count_arxiv_ids_by_keyword_strategy(KeywordStrategy::SmallLabel, outdated).unwrap(), 1);
+        assert_eq!(store.count_arxiv_ids_by_keyword_strategy(KeywordStrategy::SmallLabel, None).unwrap(), 2);
+
+        let pipeline = pipeline();
+        pipeline.process_pages(&store, store.keyword_strategy_pages(1, KeywordStrategy::SmallLabel, outdated)).unwrap();
+
+        // only the outdated small_label row was re-extracted
+        assert_eq!(store.extraction_attempts("2101.00002"), 1);
+        assert_eq!(store.provenance("2101.00002").unwrap().extractor_version, EXTRACTOR_VERSION);
+        assert_eq!(store.keywords("2101.00002").unwrap(), vec!["flat source".to_string()]);
+        assert_eq!(store.extraction_attempts("2101.00003"), 0);
+        assert_eq!(store.extraction_attempts("2101.00004"), 0);
+        assert_eq!(store.count_arxiv_ids_by_keyword_strategy(KeywordStrategy::SmallLabel, outdated).unwrap(), 0);
+    }
+
+    struct PanickingSource;
+
+    impl PaperSource for PanickingSource {
+        fn locate(&self, _arxiv_id: &ArxivId) -> AppResult<PdfLocation> {
+            panic!("malformed PDF");
+        }
+
+        fn read(&self, _location: &PdfLocation) -> AppResult<Vec<u8>> {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn records_panics_as_attempts() {
+        let store = MemoryPaperStore::new();
+        store.insert_arxiv_metadata(paper("2101.00002")).unwrap();
+
+        let extractor = ContentExtractor::new()
+            .with_paper_source(Box::new(PanickingSource));
+        let pipeline = ExtractionPipeline::new(extractor, ThreadPool::new(1));
+        pipeline.process_pages(&store, store.arxiv_id_pages(10)).unwrap();
+        pipeline.process_pages(&store, store.pending_arxiv_id_pages(10, None)).unwrap();
+
+        assert_eq!(store.extraction_status("2101.00002").as_deref(), Some("PANIC"));
+        assert_eq!(store.extraction_attempts("2101.00002"), 2);
+        assert_eq!(store.count_pending_arxiv_ids(Some(2)).unwrap(), 0);
+    }
+}
@@ -1,48 +1,126 @@
 mod model;
 mod client;
+pub mod local;
+pub mod rate;
+pub mod source;
 
+use log::{debug, warn};
+use crate::cache::{CacheEntry, PdfCache};
+use crate::content::PaperText;
 use crate::err::{AppError, AppResult};
-use crate::fetch::client::GcsClient;
+use crate::fetch::source::{GcsSource, PaperSource};
+use crate::metadata::id::ArxivId;
 
 // const MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
 
-/// Downloading tool to ensure that we fairly download PDFs within the suggested rate limit of
-/// 4 requests/second plus a 1-second sleep
+/// Downloading tool that reads PDFs from a [PaperSource] and converts them to text. The default
+/// GCS source keeps all threads together within the bucket's suggested budget of 4 requests a
+/// second, see [rate::RateLimiter].
 pub struct PaperDownloader {
-    gcs_client: GcsClient,
+    source: Box<dyn PaperSource>,
+    cache: Option<PdfCache>,
+}
+
+/// a paper's PDF and where it came from
+struct FetchedPdf {
+    version: Option<u32>,
+    source_object: Option<String>,
+    source_generation: Option<i64>,
+    bytes: Vec<u8>,
+}
+
+impl Default for PaperDownloader {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PaperDownloader {
+    /// downloads from the public GCS bucket
     pub fn new() -> Self {
         Self {
-            gcs_client: GcsClient::new(),
+            source: Box::new(GcsSource::new()),
+            cache: None,
         }
     }
 
+    /// read PDFs from somewhere other than the public GCS bucket
+    pub fn with_source(mut self, source: Box<dyn PaperSource>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// serve PDFs from a local cache where possible, and add the ones downloaded to it
+    pub fn with_cache(mut self, cache: PdfCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     /// downloads the respective arxiv paper using the id and fetches the text content of the paper
-    pub fn fetch_paper_content(&self, arxiv_id: &str) -> AppResult<String> {
-        // download the file
-        let body_bytes = self.download_paper_pdf(arxiv_id)?;
-        let pdf_content = pdf_extract::extract_text_from_mem(&body_bytes)?;
-        Ok(pdf_content)
+    pub fn fetch_paper_content(&self, arxiv_id: &ArxivId) -> AppResult<PaperText> {
+        let pdf = self.fetch_paper_pdf(arxiv_id)?;
+        let pdf_content = pdf_extract::extract_text_from_mem(&pdf.bytes)?;
+        Ok(PaperText {
+            arxiv_id: arxiv_id.base_id(),
+            version: pdf.version,
+            text: pdf_content,
+            source_object: pdf.source_object,
+            source_generation: pdf.source_generation,
+        })
     }
 
-    fn download_paper_pdf(&self, id: &str) -> AppResult<Vec<u8>> {
+    /// the PDF of a paper, from the cache if it holds the content and from the source otherwise
+    fn fetch_paper_pdf(&self, arxiv_id: &ArxivId) -> AppResult<FetchedPdf> {
+        // a pinned version that is already cached needs no lookup at the source
+        if let (Some(cache), Some(version)) = (&self.cache, arxiv_id.version()) {
+            if let Some(entry) = cache.lookup(&arxiv_id.base_id(), version)? {
+                if let Some(bytes) = cache.get(&entry)? {
+                    debug!("{}: cache hit on {}", arxiv_id, entry.md5);
+                    return Ok(FetchedPdf {
+                        version: Some(version),
+                        source_object: entry.object_name,
+                        source_generation: entry.generation,
+                        bytes,
+                    });
+                }
+            }
+        }
 
-        let response = self.gcs_client.list_objects(glob_factory(id))?;
-        let gcs_object = response.take_most_recent()
-            .ok_or(AppError::NoBucketObject(id.to_string()))?;
+        let location = self.source.locate(arxiv_id)?;
+        let fetched = |bytes| FetchedPdf {
+            version: location.version,
+            source_object: Some(location.name.clone()),
+            source_generation: location.generation,
+            bytes,
+        };
 
-        let contents = self.gcs_client.download_object_pdf(gcs_object)?;
-        Ok(contents)
-    }
-}
+        // only content with a known hash can be looked up in the cache
+        let (Some(cache), Some(md5)) = (&self.cache, &location.md5) else {
+            return Ok(fetched(self.source.read(&location)?));
+        };
 
-fn glob_factory(arxiv_id: &str) -> String {
+        let entry = CacheEntry {
+            arxiv_id: location.arxiv_id.clone(),
+            version: location.version.unwrap_or(0),
+            md5: md5.clone(),
+            object_name: Some(location.name.clone()),
+            generation: location.generation,
+        };
 
-    let items = arxiv_id.split(".").collect::<Vec<_>>();
-    assert_eq!(2, items.len(), "arxiv id should always be split into two parts");
-    let year = items.first().expect("year should always be present");
+        if let Some(bytes) = cache.get(&entry)? {
+            debug!("{}: cache hit on {}", arxiv_id, entry.md5);
+            return Ok(fetched(bytes));
+        }
 
-    format!("arxiv/arxiv/pdf/{}/{}**", year, arxiv_id)
+        // download the file
+        let body_bytes = self.source.read(&location)?;
+        match cache.put(&entry, &body_bytes) {
+            Ok(()) => {}
+            // a bad download shouldn't be extracted either
+            Err(err @ AppError::ChecksumMismatch { .. }) => return Err(err),
+            Err(err) => warn!("{}: failed to cache PDF: {}", arxiv_id, err),
+        }
+
+        Ok(fetched(body_bytes))
+    }
 }
@@ -1,27 +1,37 @@
 use std::io::Read;
+use std::sync::Arc;
 use ureq::{Agent, Body};
 use ureq::http::Response;
 use crate::err::{AppError, AppResult};
-use crate::fetch::model::{GcsListObjectResponse, GcsObject};
+use crate::fetch::model::GcsListObjectResponse;
+use crate::fetch::rate::RateLimiter;
 
 pub struct GcsClient {
     agent: Agent,
+    list_limiter: Option<Arc<RateLimiter>>,
+    download_limiter: Option<Arc<RateLimiter>>,
 }
 
 impl GcsClient {
     pub fn new() -> Self {
         Self {
             agent: ureq::agent(),
+            list_limiter: None,
+            download_limiter: None,
         }
     }
 
-    pub fn download_object_pdf(&self, object: GcsObject) -> AppResult<Vec<u8>> {
-        let payload_size = object.size();
-        if object.content_type != "application/pdf" {
-            return Err(AppError::Other(format!("object '{}' content type is not PDF", object.id)))
-        }
+    /// throttles listings and downloads separately. `None` leaves that kind of call unlimited.
+    pub fn with_rate_limits(mut self, list: Option<Arc<RateLimiter>>, download: Option<Arc<RateLimiter>>) -> Self {
+        self.list_limiter = list;
+        self.download_limiter = download;
+        self
+    }
 
-        let mut response = self.agent.get(object.media_link)
+    /// downloads an object from its media link. `payload_size` only sizes the buffer.
+    pub fn download_pdf(&self, media_link: &str, payload_size: usize) -> AppResult<Vec<u8>> {
+        throttle(&self.download_limiter);
+        let mut response = self.agent.get(media_link)
             .call()?;
 
         check_response_code(&response)?;
@@ -34,6 +44,7 @@ impl GcsClient {
     }
 
     pub fn list_objects(&self, match_glob: String) -> AppResult<GcsListObjectResponse> {
+        throttle(&self.list_limiter);
         let mut response = self.agent.get("https://storage.googleapis.com/storage/v1/b/arxiv-dataset/o")
             .query("matchGlob", match_glob)
             .call()?;
@@ -46,6 +57,12 @@ impl GcsClient {
     }
 }
 
+fn throttle(limiter: &Option<Arc<RateLimiter>>) {
+    if let Some(limiter) = limiter {
+        limiter.acquire();
+    }
+}
+
 fn check_response_code(resp: &Response<Body>) -> AppResult<()> {
     if !resp.status().is_success() {
         return Err(AppError::HttpStatusError(resp.status()))
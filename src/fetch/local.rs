@@ -0,0 +1,108 @@
+use std::path::{Path, PathBuf};
+use crate::err::{AppError, AppResult};
+use crate::fetch::source::{PaperSource, PdfLocation};
+use crate::metadata::id::ArxivId;
+
+/// highest version looked for when an id doesn't pin one
+const MAX_PROBED_VERSION: u32 = 64;
+
+/// A local copy of the bucket's `arxiv/` prefix, e.g. made with `gsutil rsync`: new-scheme papers
+/// under `<root>/arxiv/pdf/YYMM/YYMM.NNNNNvN.pdf` and legacy papers under
+/// `<root>/<archive>/pdf/YYMM/YYMMNNNvN.pdf`.
+pub struct LocalTreeSource {
+    root: PathBuf,
+}
+
+impl LocalTreeSource {
+    pub fn new<PathT: Into<PathBuf>>(root: PathT) -> Self {
+        Self {
+            root: root.into(),
+        }
+    }
+
+    fn pdf_path(&self, arxiv_id: &ArxivId, version: u32) -> PathBuf {
+        self.root
+            .join(arxiv_id.archive().unwrap_or("arxiv"))
+            .join("pdf")
+            .join(arxiv_id.yymm())
+            .join(format!("{}v{}.pdf", arxiv_id.file_stem(), version))
+    }
+}
+
+impl PaperSource for LocalTreeSource {
+    fn locate(&self, arxiv_id: &ArxivId) -> AppResult<PdfLocation> {
+        locate_local(arxiv_id, &self.root, |version| self.pdf_path(arxiv_id, version))
+    }
+
+    fn read(&self, location: &PdfLocation) -> AppResult<Vec<u8>> {
+        Ok(std::fs::read(&location.uri)?)
+    }
+}
+
+/// A single directory of `<id>vN.pdf` files, such as `2101.00001v2.pdf`. Legacy ids drop the slash,
+/// as in `hep-th9901001v1.pdf`.
+pub struct FlatDirSource {
+    dir: PathBuf,
+}
+
+impl FlatDirSource {
+    pub fn new<PathT: Into<PathBuf>>(dir: PathT) -> Self {
+        Self {
+            dir: dir.into(),
+        }
+    }
+
+    fn pdf_path(&self, arxiv_id: &ArxivId, version: u32) -> PathBuf {
+        let archive = arxiv_id.archive().unwrap_or_default();
+        self.dir.join(format!("{}{}v{}.pdf", archive, arxiv_id.file_stem(), version))
+    }
+}
+
+impl PaperSource for FlatDirSource {
+    fn locate(&self, arxiv_id: &ArxivId) -> AppResult<PdfLocation> {
+        locate_local(arxiv_id, &self.dir, |version| self.pdf_path(arxiv_id, version))
+    }
+
+    fn read(&self, location: &PdfLocation) -> AppResult<Vec<u8>> {
+        Ok(std::fs::read(&location.uri)?)
+    }
+}
+
+/// finds the pinned version, or else the highest version, by probing paths rather than listing
+/// directories that may hold a whole month of papers
+fn locate_local<PathForT>(arxiv_id: &ArxivId, root: &Path, path_for: PathForT) -> AppResult<PdfLocation>
+where
+    PathForT: Fn(u32) -> PathBuf,
+{
+    let found = match arxiv_id.version() {
+        Some(version) => Some((version, path_for(version))).filter(|(_, path)| path.is_file()),
+        None => {
+            // versions are numbered without gaps, but a mirror may only hold the latest ones
+            let mut latest = None;
+            for version in 1..=MAX_PROBED_VERSION {
+                let path = path_for(version);
+                if path.is_file() {
+                    latest = Some((version, path));
+                } else if latest.is_some() {
+                    break;
+                }
+            }
+            latest
+        }
+    };
+
+    let (version, path) = found.ok_or_else(|| AppError::PdfNotFound {
+        arxiv_id: arxiv_id.to_string(),
+        location: root.display().to_string(),
+    })?;
+    let path = path.display().to_string();
+    Ok(PdfLocation {
+        arxiv_id: arxiv_id.base_id(),
+        version: Some(version),
+        name: path.clone(),
+        uri: path,
+        md5: None,
+        generation: None,
+        size: None,
+    })
+}
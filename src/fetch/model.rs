@@ -1,32 +1,55 @@
 use serde::Deserialize;
+use crate::cache::gcs_md5_to_hex;
+use crate::err::AppResult;
 
 #[derive(Deserialize)]
 #[serde(rename_all(deserialize = "camelCase"))]
 pub struct GcsObject {
-    pub(crate) bucket: String,
     pub(crate) content_type: String,
     pub(crate) id: String,
     pub(crate) md5_hash: String,
     pub(crate) media_link: String,
     pub(crate) name: String,
-    pub(crate) self_link: String,
     size: String,
+    /// the JSON API encodes the 64-bit generation as a string
+    #[serde(default)]
+    generation: Option<String>,
 }
 
 impl GcsObject {
     pub fn size(&self) -> usize {
         self.size.parse().expect("size should always be usize-parsable")
     }
+
+    /// identifies the exact content of the object; changes whenever the object is overwritten
+    pub fn generation(&self) -> Option<i64> {
+        self.generation.as_deref()?.parse().ok()
+    }
+
+    /// the object's MD5 as hex; the JSON API reports it base64-encoded
+    pub fn md5_hex(&self) -> AppResult<String> {
+        gcs_md5_to_hex(&self.md5_hash)
+    }
+
+    /// version number parsed from an object name like `.../2101.00001v2.pdf`
+    pub fn version(&self) -> Option<u32> {
+        let file_name = self.name.rsplit('/').next()?;
+        let stem = file_name.strip_suffix(".pdf").unwrap_or(file_name);
+        let (_, version) = stem.rsplit_once('v')?;
+        version.parse().ok()
+    }
 }
 
 #[derive(Deserialize)]
 pub(crate) struct GcsListObjectResponse {
     pub(crate) items: Vec<GcsObject>,
-    pub(crate) kind: String,
 }
 
 impl GcsListObjectResponse {
+    /// takes the object with the highest version. Versions are compared numerically, since the
+    /// listing is sorted by name and would put `v10` before `v2`.
     pub fn take_most_recent(self) -> Option<GcsObject> {
-        self.items.into_iter().last()
+        self.items.into_iter()
+            .max_by_key(|object| object.version())
     }
 }
@@ -0,0 +1,205 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use log::{debug, info};
+
+/// bucket object listings per second, shared by all extractor threads
+pub const DEFAULT_LIST_RATE: f64 = 4.0;
+/// bucket downloads per second, shared by all extractor threads
+pub const DEFAULT_DOWNLOAD_RATE: f64 = 4.0;
+
+/// the slowest rate a limiter accepts, one request every 11.6 days. Slower rates make waits
+/// too long to represent as a [Duration].
+pub const MIN_RATE: f64 = 1e-6;
+
+/// how often a limiter logs its running totals, in requests
+const REPORT_EVERY: u64 = 1000;
+
+struct BucketState {
+    /// negative when callers have reserved tokens that haven't accrued yet
+    tokens: f64,
+    refilled: Instant,
+}
+
+/// Token bucket shared between threads. Each request takes a token, tokens accrue at a fixed rate
+/// up to `burst`, and a caller that finds the bucket empty reserves the next token and sleeps
+/// until it accrues. Waiting callers therefore queue up in order rather than polling.
+pub struct RateLimiter {
+    name: &'static str,
+    /// tokens per second
+    rate: f64,
+    burst: f64,
+    state: Mutex<BucketState>,
+    requests: AtomicU64,
+    throttled: AtomicU64,
+    waited_micros: AtomicU64,
+}
+
+/// how much a [RateLimiter] has held its callers back
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottleStats {
+    pub requests: u64,
+    /// requests that had to wait
+    pub throttled: u64,
+    pub total_wait: Duration,
+}
+
+impl RateLimiter {
+    /// allows `rate` requests per second, and up to `burst` back to back after being idle.
+    ///
+    /// Panics unless `rate` is finite and at least [MIN_RATE].
+    pub fn new(name: &'static str, rate: f64, burst: u32) -> Self {
+        assert!(rate.is_finite() && rate >= MIN_RATE, "{} rate must be finite and at least {}, got {}", name, MIN_RATE, rate);
+        let burst = burst.max(1) as f64;
+        Self {
+            name,
+            rate,
+            burst,
+            state: Mutex::new(BucketState {
+                tokens: burst,
+                refilled: Instant::now(),
+            }),
+            requests: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+            waited_micros: AtomicU64::new(0),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// blocks until the caller may make a request, returning how long it waited
+    pub fn acquire(&self) -> Duration {
+        let wait = {
+            let mut state = self.lock();
+            let now = Instant::now();
+            let accrued = now.duration_since(state.refilled).as_secs_f64() * self.rate;
+            state.tokens = (state.tokens + accrued).min(self.burst);
+            state.refilled = now;
+
+            state.tokens -= 1.0;
+            if state.tokens >= 0.0 {
+                Duration::ZERO
+            } else {
+                Duration::from_secs_f64(-state.tokens / self.rate)
+            }
+        };
+
+        if !wait.is_zero() {
+            debug!("waited {} ms for the {} budget", wait.as_millis(), self.name);
+            std::thread::sleep(wait);
+            self.throttled.fetch_add(1, Ordering::Relaxed);
+            self.waited_micros.fetch_add(wait.as_micros() as u64, Ordering::Relaxed);
+        }
+
+        let requests = self.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        if requests.is_multiple_of(REPORT_EVERY) {
+            info!("{} budget: {}", self.name, self.stats());
+        }
+
+        wait
+    }
+
+    pub fn stats(&self) -> ThrottleStats {
+        ThrottleStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+            total_wait: Duration::from_micros(self.waited_micros.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BucketState> {
+        self.state.lock()
+            .expect("rate limiter lock poisoned")
+    }
+}
+
+impl std::fmt::Display for ThrottleStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let average_ms = if self.throttled > 0 {
+            self.total_wait.as_secs_f64() * 1000.0 / self.throttled as f64
+        } else {
+            0.0
+        };
+
+        write!(
+            f,
+            "{} request(s), {} throttled, {:.1}s waiting in total ({:.0} ms per throttled request)",
+            self.requests,
+            self.throttled,
+            self.total_wait.as_secs_f64(),
+            average_ms
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn threads_share_the_budget() {
+        const RATE: f64 = 50.0;
+        const BURST: u32 = 3;
+        const THREADS: usize = 4;
+        const REQUESTS_PER_THREAD: usize = 10;
+
+        let limiter = Arc::new(RateLimiter::new("test", RATE, BURST));
+        let started = Instant::now();
+        let threads = (0..THREADS)
+            .map(|_| {
+                let limiter = limiter.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..REQUESTS_PER_THREAD {
+                        limiter.acquire();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let elapsed = started.elapsed().as_secs_f64();
+
+        // never more than the burst plus what accrued while running
+        let requests = (THREADS * REQUESTS_PER_THREAD) as f64;
+        assert!(requests <= RATE * elapsed + BURST as f64, "{} requests in {:.3}s", requests, elapsed);
+        // and not held back much longer than needed
+        let needed = (requests - BURST as f64) / RATE;
+        assert!(elapsed < needed + 0.5, "took {:.3}s, needed {:.3}s", elapsed, needed);
+
+        let stats = limiter.stats();
+        assert_eq!(stats.requests, requests as u64);
+        // late starting threads can find tokens that accrued in the meantime
+        assert!(stats.throttled <= requests as u64 - BURST as u64);
+    }
+
+    #[test]
+    fn burst_is_not_throttled() {
+        let limiter = RateLimiter::new("test", 1.0, 3);
+        for _ in 0..3 {
+            assert_eq!(limiter.acquire(), Duration::ZERO);
+        }
+        assert_eq!(limiter.stats().throttled, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "finite and at least")]
+    fn rejects_zero_rate() {
+        RateLimiter::new("test", 0.0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "finite and at least")]
+    fn rejects_tiny_rate() {
+        RateLimiter::new("test", 1e-20, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "finite and at least")]
+    fn rejects_nan_rate() {
+        RateLimiter::new("test", f64::NAN, 1);
+    }
+}
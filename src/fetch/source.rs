@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use crate::err::{AppError, AppResult};
+use crate::fetch::client::GcsClient;
+use crate::fetch::rate::{RateLimiter, DEFAULT_DOWNLOAD_RATE, DEFAULT_LIST_RATE};
+use crate::metadata::id::ArxivId;
+
+/// where a [PaperSource] found a paper's PDF, before it is read
+#[derive(Debug, Clone)]
+pub struct PdfLocation {
+    /// unversioned arxiv id
+    pub arxiv_id: String,
+    pub version: Option<u32>,
+    /// bucket object name or file path, recorded as the source of the extraction
+    pub name: String,
+    /// URL or path the PDF is read from
+    pub uri: String,
+    /// hex MD5 of the PDF, if the source knows it without reading the PDF
+    pub md5: Option<String>,
+    pub generation: Option<i64>,
+    pub size: Option<u64>,
+}
+
+/// Somewhere arXiv PDFs can be read from. Finding a PDF and reading it are separate steps, so the
+/// PDF cache can skip the read when it already holds the content.
+pub trait PaperSource: Send + Sync {
+    /// finds a paper's PDF: the pinned version if the id has one, the latest otherwise
+    fn locate(&self, arxiv_id: &ArxivId) -> AppResult<PdfLocation>;
+
+    /// reads a PDF found by [Self::locate]
+    fn read(&self, location: &PdfLocation) -> AppResult<Vec<u8>>;
+}
+
+/// the public `arxiv-dataset` bucket on Google Cloud Storage
+pub struct GcsSource {
+    client: GcsClient,
+}
+
+impl Default for GcsSource {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GcsSource {
+    /// throttled to [DEFAULT_LIST_RATE] listings and [DEFAULT_DOWNLOAD_RATE] downloads a second
+    pub fn new() -> Self {
+        Self::unthrottled().with_rate_limits(
+            Some(Arc::new(RateLimiter::new("list", DEFAULT_LIST_RATE, 1))),
+            Some(Arc::new(RateLimiter::new("download", DEFAULT_DOWNLOAD_RATE, 1))),
+        )
+    }
+
+    pub fn unthrottled() -> Self {
+        Self {
+            client: GcsClient::new(),
+        }
+    }
+
+    /// replaces the listing and download budgets. Limiters can be shared with other sources, and
+    /// `None` leaves that kind of call unlimited.
+    pub fn with_rate_limits(mut self, list: Option<Arc<RateLimiter>>, download: Option<Arc<RateLimiter>>) -> Self {
+        self.client = self.client.with_rate_limits(list, download);
+        self
+    }
+}
+
+impl PaperSource for GcsSource {
+    fn locate(&self, arxiv_id: &ArxivId) -> AppResult<PdfLocation> {
+        let response = self.client.list_objects(glob_factory(arxiv_id))?;
+        let gcs_object = response.take_most_recent()
+            .ok_or(AppError::NoBucketObject(arxiv_id.to_string()))?;
+
+        if gcs_object.content_type != "application/pdf" {
+            return Err(AppError::Other(format!("object '{}' content type is not PDF", gcs_object.id)))
+        }
+
+        Ok(PdfLocation {
+            arxiv_id: arxiv_id.base_id(),
+            version: gcs_object.version(),
+            md5: Some(gcs_object.md5_hex()?),
+            generation: gcs_object.generation(),
+            size: Some(gcs_object.size() as u64),
+            name: gcs_object.name,
+            uri: gcs_object.media_link,
+        })
+    }
+
+    fn read(&self, location: &PdfLocation) -> AppResult<Vec<u8>> {
+        self.client.download_pdf(&location.uri, location.size.unwrap_or_default() as usize)
+    }
+}
+
+/// builds the object glob for a paper in the `arxiv-dataset` bucket. New-scheme papers live under
+/// `arxiv/arxiv/pdf/YYMM/YYMM.NNNNNvN.pdf`, legacy papers under their archive as
+/// `arxiv/<archive>/pdf/YYMM/YYMMNNNvN.pdf`. Without an explicit version, every version matches.
+fn glob_factory(arxiv_id: &ArxivId) -> String {
+    let archive = arxiv_id.archive().unwrap_or("arxiv");
+    let version = arxiv_id.version()
+        .map(|version| version.to_string())
+        .unwrap_or_else(|| "*".to_string());
+
+    format!("arxiv/{}/pdf/{}/{}v{}.pdf", archive, arxiv_id.yymm(), arxiv_id.file_stem(), version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(value: &str) -> String {
+        glob_factory(&ArxivId::parse(value).unwrap())
+    }
+
+    #[test]
+    fn globs_new_scheme_ids_under_arxiv() {
+        assert_eq!(glob("2101.00001"), "arxiv/arxiv/pdf/2101/2101.00001v*.pdf");
+        assert_eq!(glob("2101.00001v3"), "arxiv/arxiv/pdf/2101/2101.00001v3.pdf");
+        assert_eq!(glob("0704.0001"), "arxiv/arxiv/pdf/0704/0704.0001v*.pdf");
+    }
+
+    #[test]
+    fn globs_legacy_ids_under_their_archive() {
+        assert_eq!(glob("cs/0601001"), "arxiv/cs/pdf/0601/0601001v*.pdf");
+        assert_eq!(glob("math.GT/0309136v1"), "arxiv/math/pdf/0309/0309136v1.pdf");
+        assert_eq!(glob("solv-int/9901001"), "arxiv/solv-int/pdf/9901/9901001v*.pdf");
+    }
+}
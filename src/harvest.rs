@@ -0,0 +1,237 @@
+mod model;
+
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+use std::io::BufReader;
+use std::time::Duration;
+use chrono::NaiveDate;
+use log::{info, warn};
+use ureq::Agent;
+use ureq::http::StatusCode;
+use crate::err::{AppError, AppResult};
+use crate::harvest::model::OaiPmhResponse;
+use crate::metadata::ArxivMetadata;
+
+/// arXiv's public OAI-PMH endpoint
+pub const DEFAULT_OAI_ENDPOINT: &str = "https://oaipmh.arxiv.org/oai";
+
+/// how long to back off when a 503 carries no usable `Retry-After`
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(10);
+
+/// OAI-PMH metadata formats served by arXiv that we know how to map
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataFormat {
+    /// structured authors, but only the first version's date
+    Arxiv,
+    /// every version with its submission date, mirroring the kaggle dump
+    ArxivRaw,
+}
+
+impl MetadataFormat {
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            MetadataFormat::Arxiv => "arXiv",
+            MetadataFormat::ArxivRaw => "arXivRaw",
+        }
+    }
+}
+
+impl Display for MetadataFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.prefix())
+    }
+}
+
+/// selects which records a harvest pulls
+#[derive(Debug, Clone)]
+pub struct HarvestRequest {
+    pub format: MetadataFormat,
+    /// OAI set spec, such as `cs` or `math`
+    pub set: Option<String>,
+    /// inclusive lower bound on the record datestamp
+    pub from: Option<NaiveDate>,
+    /// inclusive upper bound on the record datestamp
+    pub until: Option<NaiveDate>,
+    /// continues an earlier harvest from this token instead of starting a new list. The token
+    /// encodes the set and dates, so only `format` still applies.
+    pub resumption_token: Option<String>,
+}
+
+/// one page of a `ListRecords` response
+#[derive(Default)]
+pub struct HarvestPage {
+    pub records: Vec<ArxivMetadata>,
+    /// records the server reported as deleted. These carry no metadata and are left out.
+    pub deleted_records: u64,
+    /// fetches the following page; `None` on the last one
+    pub resumption_token: Option<String>,
+    pub complete_list_size: Option<u64>,
+}
+
+/// Pulls metadata records from an OAI-PMH endpoint, following resumption tokens until the list is
+/// exhausted. Requests are spaced out by the configured delay, and 503 responses are retried after
+/// the server's `Retry-After`.
+pub struct OaiHarvester {
+    agent: Agent,
+    endpoint: String,
+    request_delay: Duration,
+    max_retries: u32,
+}
+
+impl OaiHarvester {
+    pub fn new<StrT: Into<String>>(endpoint: StrT) -> Self {
+        let agent = Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+
+        Self {
+            agent,
+            endpoint: endpoint.into(),
+            request_delay: Duration::from_secs(3),
+            max_retries: 5,
+        }
+    }
+
+    pub fn with_request_delay(mut self, request_delay: Duration) -> Self {
+        self.request_delay = request_delay;
+        self
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// lazily harvests every record matching the request
+    pub fn harvest(&self, request: HarvestRequest) -> HarvestIter<'_> {
+        let next_page = match &request.resumption_token {
+            Some(token) => PageQuery::Resume(token.clone()),
+            None => PageQuery::Initial,
+        };
+        HarvestIter {
+            harvester: self,
+            request,
+            pending: VecDeque::new(),
+            next_page: Some(next_page),
+            pages_fetched: 0,
+            deleted_records: 0,
+        }
+    }
+
+    fn list_records(&self, query: &[(&str, &str)]) -> AppResult<OaiPmhResponse> {
+        let mut attempt = 0;
+        loop {
+            let mut response = self.agent.get(&self.endpoint)
+                .query_pairs(query.iter().copied())
+                .call()?;
+
+            let status = response.status();
+            if status == StatusCode::SERVICE_UNAVAILABLE && attempt < self.max_retries {
+                let retry_after = response.headers().get("Retry-After")
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+                    .map(Duration::from_secs)
+                    .unwrap_or(DEFAULT_RETRY_AFTER);
+                warn!("OAI-PMH endpoint busy, retrying in {}s", retry_after.as_secs());
+                std::thread::sleep(retry_after);
+                attempt += 1;
+                continue;
+            }
+
+            if !status.is_success() {
+                return Err(AppError::HttpStatusError(status));
+            }
+
+            let body_reader = BufReader::new(response.body_mut().as_reader());
+            return OaiPmhResponse::parse(body_reader);
+        }
+    }
+}
+
+enum PageQuery {
+    Initial,
+    Resume(String),
+}
+
+/// iterator over harvested records. An error ends the iteration.
+pub struct HarvestIter<'a> {
+    harvester: &'a OaiHarvester,
+    request: HarvestRequest,
+    pending: VecDeque<ArxivMetadata>,
+    next_page: Option<PageQuery>,
+    pages_fetched: u64,
+    deleted_records: u64,
+}
+
+impl HarvestIter<'_> {
+    pub fn pages_fetched(&self) -> u64 {
+        self.pages_fetched
+    }
+
+    /// records the server reported as deleted. These carry no metadata and are not yielded.
+    pub fn deleted_records(&self) -> u64 {
+        self.deleted_records
+    }
+
+    /// fetches the next page of records, or `None` once the list is exhausted. Records already
+    /// fetched through the [Iterator] impl aren't returned again.
+    pub fn next_page(&mut self) -> Option<AppResult<HarvestPage>> {
+        let page = self.next_page.take()?;
+        Some(self.fetch_page(page))
+    }
+
+    fn fetch_page(&mut self, page: PageQuery) -> AppResult<HarvestPage> {
+        if self.pages_fetched > 0 {
+            std::thread::sleep(self.harvester.request_delay);
+        }
+
+        let from = self.request.from.map(|date| date.to_string());
+        let until = self.request.until.map(|date| date.to_string());
+        let mut query = vec![("verb", "ListRecords")];
+        match &page {
+            PageQuery::Initial => {
+                query.push(("metadataPrefix", self.request.format.prefix()));
+                if let Some(set) = self.request.set.as_deref() {
+                    query.push(("set", set));
+                }
+                if let Some(from) = from.as_deref() {
+                    query.push(("from", from));
+                }
+                if let Some(until) = until.as_deref() {
+                    query.push(("until", until));
+                }
+            }
+            PageQuery::Resume(token) => query.push(("resumptionToken", token)),
+        }
+
+        let page = self.harvester.list_records(&query)?
+            .into_page(self.request.format)?;
+        self.pages_fetched += 1;
+        self.deleted_records += page.deleted_records;
+
+        if let Some(size) = page.complete_list_size {
+            info!("harvested page {} of a {} record list", self.pages_fetched, size);
+        }
+        self.next_page = page.resumption_token.clone().map(PageQuery::Resume);
+
+        Ok(page)
+    }
+}
+
+impl Iterator for HarvestIter<'_> {
+    type Item = AppResult<ArxivMetadata>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(metadata) = self.pending.pop_front() {
+                return Some(Ok(metadata));
+            }
+
+            match self.next_page()? {
+                Ok(page) => self.pending.extend(page.records),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
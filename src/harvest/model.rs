@@ -0,0 +1,356 @@
+use std::io::BufRead;
+use chrono::NaiveDate;
+use log::{debug, info, warn};
+use serde::Deserialize;
+use crate::err::{AppError, AppResult};
+use crate::harvest::{HarvestPage, MetadataFormat};
+use crate::metadata::{ArxivAuthor, ArxivMetadata, ArxivVersion};
+
+/// RFC 2822 layout used by the kaggle dump and `arXivRaw` version dates
+const RFC2822_GMT: &str = "%a, %-d %b %Y %H:%M:%S GMT";
+
+#[derive(Deserialize)]
+pub(crate) struct OaiPmhResponse {
+    #[serde(default)]
+    pub(crate) error: Vec<OaiError>,
+    #[serde(rename = "ListRecords")]
+    pub(crate) list_records: Option<ListRecords>,
+}
+
+impl OaiPmhResponse {
+    pub(crate) fn parse<ReadT: BufRead>(reader: ReadT) -> AppResult<Self> {
+        quick_xml::de::from_reader(reader)
+            .map_err(|err| AppError::Other(format!("malformed OAI-PMH response: {}", err)))
+    }
+
+    /// maps the records of a `ListRecords` response, skipping deleted ones. `noRecordsMatch` is an
+    /// empty last page rather than an error.
+    pub(crate) fn into_page(self, format: MetadataFormat) -> AppResult<HarvestPage> {
+        if let Some(err) = self.error.first() {
+            if err.code == "noRecordsMatch" {
+                info!("no records match the harvest request");
+                return Ok(HarvestPage::default());
+            }
+
+            return Err(AppError::Other(format!("OAI-PMH error {}: {}", err.code, err.message.trim())));
+        }
+
+        let Some(list_records) = self.list_records else {
+            return Err(AppError::Other("OAI-PMH response has no ListRecords element".to_string()));
+        };
+
+        let mut page = HarvestPage::default();
+        for record in list_records.records {
+            if record.header.is_deleted() {
+                debug!("skipping deleted record {}", record.header.identifier);
+                page.deleted_records += 1;
+                continue;
+            }
+
+            let datestamp = record.header.datestamp;
+            match record.metadata.and_then(|metadata| metadata.into_arxiv_metadata(datestamp)) {
+                Some(metadata) => page.records.push(metadata),
+                None => warn!("record {} has no {} metadata", record.header.identifier, format),
+            }
+        }
+
+        if let Some(token) = list_records.resumption_token {
+            page.resumption_token = token.next_token().map(str::to_string);
+            page.complete_list_size = token.complete_list_size;
+        }
+
+        Ok(page)
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct OaiError {
+    #[serde(rename = "@code")]
+    pub(crate) code: String,
+    #[serde(rename = "$text", default)]
+    pub(crate) message: String,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ListRecords {
+    #[serde(rename = "record", default)]
+    pub(crate) records: Vec<OaiRecord>,
+    #[serde(rename = "resumptionToken")]
+    pub(crate) resumption_token: Option<ResumptionToken>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ResumptionToken {
+    #[serde(rename = "$text")]
+    pub(crate) token: Option<String>,
+    #[serde(rename = "@completeListSize")]
+    pub(crate) complete_list_size: Option<u64>,
+}
+
+impl ResumptionToken {
+    /// an empty token marks the last page of a list
+    pub(crate) fn next_token(&self) -> Option<&str> {
+        self.token.as_deref()
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct OaiRecord {
+    pub(crate) header: OaiHeader,
+    pub(crate) metadata: Option<OaiMetadata>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct OaiHeader {
+    #[serde(rename = "@status")]
+    pub(crate) status: Option<String>,
+    pub(crate) identifier: String,
+    pub(crate) datestamp: Option<String>,
+}
+
+impl OaiHeader {
+    pub(crate) fn is_deleted(&self) -> bool {
+        self.status.as_deref() == Some("deleted")
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct OaiMetadata {
+    #[serde(rename = "arXiv")]
+    pub(crate) arxiv: Option<ArxivFormat>,
+    #[serde(rename = "arXivRaw")]
+    pub(crate) arxiv_raw: Option<ArxivRawFormat>,
+}
+
+impl OaiMetadata {
+    /// maps the record into kaggle-style metadata. The header datestamp, the record's last
+    /// modification date, stands in for the dump's `update_date`.
+    pub(crate) fn into_arxiv_metadata(self, datestamp: Option<String>) -> Option<ArxivMetadata> {
+        let mut metadata = self.arxiv_raw.map(ArxivMetadata::from)
+            .or_else(|| self.arxiv.map(ArxivMetadata::from))?;
+        metadata.update_date = datestamp;
+        Some(metadata)
+    }
+}
+
+/// the `arXiv` metadata format. It has structured authors but only the creation date of the
+/// first version.
+#[derive(Deserialize)]
+pub(crate) struct ArxivFormat {
+    pub(crate) id: String,
+    pub(crate) created: Option<NaiveDate>,
+    #[serde(default)]
+    pub(crate) authors: ArxivFormatAuthors,
+    pub(crate) title: Option<String>,
+    pub(crate) categories: Option<String>,
+    pub(crate) comments: Option<String>,
+    #[serde(rename = "journal-ref")]
+    pub(crate) journal_ref: Option<String>,
+    pub(crate) doi: Option<String>,
+    #[serde(rename = "abstract")]
+    pub(crate) abstract_text: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub(crate) struct ArxivFormatAuthors {
+    #[serde(rename = "author", default)]
+    pub(crate) authors: Vec<ArxivFormatAuthor>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ArxivFormatAuthor {
+    pub(crate) keyname: String,
+    pub(crate) forenames: Option<String>,
+    pub(crate) suffix: Option<String>,
+}
+
+impl ArxivFormatAuthor {
+    fn display_name(&self) -> String {
+        [self.forenames.as_deref(), Some(self.keyname.as_str()), self.suffix.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl From<&ArxivFormatAuthor> for ArxivAuthor {
+    fn from(value: &ArxivFormatAuthor) -> Self {
+        Self {
+            last_name: value.keyname.trim().to_string(),
+            first_names: value.forenames.as_deref().unwrap_or_default().trim().to_string(),
+            suffix: value.suffix.as_deref().unwrap_or_default().trim().to_string(),
+        }
+    }
+}
+
+impl From<ArxivFormat> for ArxivMetadata {
+    fn from(value: ArxivFormat) -> Self {
+        let authors = value.authors.authors.iter()
+            .map(ArxivFormatAuthor::display_name)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let authors_parsed = value.authors.authors.iter()
+            .map(ArxivAuthor::from)
+            .collect();
+
+        let versions = value.created.into_iter()
+            .map(|created| ArxivVersion {
+                version: "v1".to_string(),
+                created: created.and_hms_opt(0, 0, 0)
+                    .expect("midnight should always be valid")
+                    .format(RFC2822_GMT)
+                    .to_string(),
+            })
+            .collect();
+
+        Self {
+            id: Some(value.id),
+            submitter: None,
+            authors: Some(authors),
+            title: value.title,
+            comments: value.comments,
+            journal_ref: value.journal_ref,
+            doi: value.doi,
+            categories: value.categories,
+            abstract_text: value.abstract_text,
+            versions,
+            authors_parsed,
+            update_date: None,
+        }
+    }
+}
+
+/// the `arXivRaw` metadata format, which mirrors the kaggle dump including every version, but has
+/// no structured authors
+#[derive(Deserialize)]
+pub(crate) struct ArxivRawFormat {
+    pub(crate) id: String,
+    pub(crate) submitter: Option<String>,
+    #[serde(rename = "version", default)]
+    pub(crate) versions: Vec<ArxivRawVersion>,
+    pub(crate) title: Option<String>,
+    pub(crate) authors: Option<String>,
+    pub(crate) categories: Option<String>,
+    pub(crate) comments: Option<String>,
+    #[serde(rename = "journal-ref")]
+    pub(crate) journal_ref: Option<String>,
+    pub(crate) doi: Option<String>,
+    #[serde(rename = "abstract")]
+    pub(crate) abstract_text: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ArxivRawVersion {
+    #[serde(rename = "@version")]
+    pub(crate) version: String,
+    pub(crate) date: String,
+}
+
+impl From<ArxivRawFormat> for ArxivMetadata {
+    fn from(value: ArxivRawFormat) -> Self {
+        let versions = value.versions.into_iter()
+            .map(|version| ArxivVersion {
+                version: version.version,
+                created: version.date,
+            })
+            .collect();
+
+        Self {
+            id: Some(value.id),
+            submitter: value.submitter,
+            authors: value.authors,
+            title: value.title,
+            comments: value.comments,
+            journal_ref: value.journal_ref,
+            doi: value.doi,
+            categories: value.categories,
+            abstract_text: value.abstract_text,
+            versions,
+            authors_parsed: Vec::new(),
+            update_date: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(xml: &str, format: MetadataFormat) -> HarvestPage {
+        OaiPmhResponse::parse(xml.as_bytes())
+            .and_then(|response| response.into_page(format))
+            .unwrap()
+    }
+
+    #[test]
+    fn maps_arxiv_list_records() {
+        let page = page(include_str!("../../tests/fixtures/oai/list_records_arxiv.xml"), MetadataFormat::Arxiv);
+        assert_eq!(page.deleted_records, 1);
+        assert_eq!(page.resumption_token.as_deref(), Some("6960524|1001"));
+        assert_eq!(page.complete_list_size, Some(2517));
+        assert_eq!(page.records.len(), 2);
+
+        let paper = &page.records[0];
+        assert_eq!(paper.id().map(String::as_str), Some("2405.00012"));
+        assert_eq!(paper.authors().map(String::as_str), Some("Ada Lovelace, Charles Babbage Jr"));
+        assert_eq!(paper.authors_parsed().len(), 2);
+        assert_eq!(paper.authors_parsed()[1].last_name, "Babbage");
+        assert_eq!(paper.authors_parsed()[1].suffix, "Jr");
+        assert_eq!(paper.categories().map(String::as_str), Some("cs.CL cs.LG"));
+        assert_eq!(paper.journal_ref().map(String::as_str), Some("Proc. Engines 2024"));
+        assert_eq!(paper.doi().map(String::as_str), Some("10.1000/engines.2024.12"));
+        assert_eq!(paper.update_date().map(String::as_str), Some("2024-05-01"));
+        assert!(paper.abstract_text().is_some_and(|text| text.contains("analytical engine")));
+
+        // the arXiv format only carries the first version's date
+        assert_eq!(paper.versions().len(), 1);
+        assert_eq!(paper.versions()[0].version(), "v1");
+        assert_eq!(paper.versions()[0].created_utc().as_deref(), Some("2024-04-29T00:00:00Z"));
+
+        assert_eq!(page.records[1].arxiv_id().unwrap().base_id(), "cs/0112017");
+    }
+
+    #[test]
+    fn maps_arxiv_raw_list_records() {
+        let page = page(include_str!("../../tests/fixtures/oai/list_records_arxiv_raw.xml"), MetadataFormat::ArxivRaw);
+        assert_eq!(page.deleted_records, 0);
+        // an empty token marks the last page
+        assert_eq!(page.resumption_token, None);
+        assert_eq!(page.records.len(), 1);
+
+        let paper = &page.records[0];
+        assert_eq!(paper.id().map(String::as_str), Some("2405.00012"));
+        assert_eq!(paper.submitter().map(String::as_str), Some("Ada Lovelace"));
+        assert_eq!(paper.authors().map(String::as_str), Some("Ada Lovelace, Charles Babbage Jr"));
+        assert!(paper.authors_parsed().is_empty());
+        assert_eq!(paper.update_date().map(String::as_str), Some("2024-05-03"));
+
+        let versions = paper.versions().iter()
+            .map(|version| (version.version(), version.created_utc()))
+            .collect::<Vec<_>>();
+        assert_eq!(versions, vec![
+            ("v1", Some("2024-04-29T17:59:58Z".to_string())),
+            ("v2", Some("2024-05-03T09:12:44Z".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn no_records_match_is_an_empty_last_page() {
+        let page = page(include_str!("../../tests/fixtures/oai/no_records_match.xml"), MetadataFormat::ArxivRaw);
+        assert!(page.records.is_empty());
+        assert_eq!(page.resumption_token, None);
+    }
+
+    #[test]
+    fn other_oai_errors_fail() {
+        let xml = r#"<OAI-PMH xmlns="http://www.openarchives.org/OAI/2.0/"><error code="badResumptionToken">expired</error></OAI-PMH>"#;
+        let err = OaiPmhResponse::parse(xml.as_bytes())
+            .and_then(|response| response.into_page(MetadataFormat::Arxiv))
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("badResumptionToken"));
+    }
+}
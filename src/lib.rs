@@ -2,7 +2,10 @@
 pub mod metadata;
 pub mod err;
 pub mod db;
+pub mod cache;
 pub mod content;
 pub mod extraction;
-mod fetch;
+pub mod fetch;
+pub mod harvest;
+pub mod storage;
 pub mod training;
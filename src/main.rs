@@ -1,7 +1,9 @@
 use crate::args::Args;
+use crate::subcommand::cache::handle_cache_command;
 use crate::subcommand::db::handle_db_command;
 use crate::subcommand::extract::extract_and_save_contents;
 use crate::subcommand::pull_data::pull_data;
+use crate::subcommand::search::search;
 use crate::subcommand::AppSubCommands;
 use clap::Parser;
 use keyword_dataset_rs::err::AppError;
@@ -19,7 +21,9 @@ fn main() -> Result<(), AppError> {
     let args = Args::parse();
     match args.command {
         AppSubCommands::DB(db_subcommand) => handle_db_command(db_subcommand),
+        AppSubCommands::Cache(cache_subcommand) => handle_cache_command(cache_subcommand),
         AppSubCommands::Extract(extract_args) => extract_and_save_contents(extract_args),
         AppSubCommands::PullTraining(args) => pull_data(args),
+        AppSubCommands::Search(args) => search(args),
     }
 }
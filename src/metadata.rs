@@ -1,12 +1,18 @@
+pub mod filter;
+pub mod id;
+pub mod input;
+pub mod parallel;
 pub mod reader;
 
-use chrono::{DateTime, FixedOffset};
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
 use serde::Deserialize;
+use crate::err::{AppError, AppResult};
+use crate::metadata::id::ArxivId;
 
 #[derive(Deserialize)]
 pub struct ArxivVersion {
-    version: String,
-    created: String,
+    pub(crate) version: String,
+    pub(crate) created: String,
 }
 
 impl ArxivVersion {
@@ -14,6 +20,13 @@ impl ArxivVersion {
         DateTime::parse_from_rfc2822(&self.created)
     }
     
+    /// the creation date as a sortable ISO-8601 UTC timestamp, e.g. `2007-04-02T19:18:42Z`
+    pub fn created_utc(&self) -> Option<String> {
+        self.created_date()
+            .ok()
+            .map(|created| to_iso8601_utc(&created))
+    }
+
     pub fn is_after(&self, date: &DateTime<FixedOffset>) -> bool {
         let Ok(created_date) = self.created_date() else {
             return false;
@@ -31,20 +44,46 @@ impl ArxivVersion {
     }
 }
 
+/// one entry of the dump's `authors_parsed` array: `[last name, first names, suffix]`
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(from = "Vec<Option<String>>")]
+pub struct ArxivAuthor {
+    pub last_name: String,
+    pub first_names: String,
+    pub suffix: String,
+}
+
+impl From<Vec<Option<String>>> for ArxivAuthor {
+    fn from(value: Vec<Option<String>>) -> Self {
+        let mut parts = value.into_iter()
+            .map(|part| part.unwrap_or_default().trim().to_string());
+
+        Self {
+            last_name: parts.next().unwrap_or_default(),
+            first_names: parts.next().unwrap_or_default(),
+            suffix: parts.next().unwrap_or_default(),
+        }
+    }
+}
+
 #[derive(Deserialize)]
 pub struct ArxivMetadata {
-    id: Option<String>,
-    submitter: Option<String>,
-    authors: Option<String>,
-    title: Option<String>,
-    comments: Option<String>,
+    pub(crate) id: Option<String>,
+    pub(crate) submitter: Option<String>,
+    pub(crate) authors: Option<String>,
+    pub(crate) title: Option<String>,
+    pub(crate) comments: Option<String>,
     #[serde(alias = "journal-ref")]
-    journal_ref: Option<String>,
-    doi: Option<String>,
-    categories: Option<String>,
+    pub(crate) journal_ref: Option<String>,
+    pub(crate) doi: Option<String>,
+    pub(crate) categories: Option<String>,
     #[serde(alias = "abstract")]
-    abstract_text: Option<String>,
-    versions: Vec<ArxivVersion>,
+    pub(crate) abstract_text: Option<String>,
+    pub(crate) versions: Vec<ArxivVersion>,
+    #[serde(default)]
+    pub(crate) authors_parsed: Vec<ArxivAuthor>,
+    /// date of the last metadata update, as `YYYY-MM-DD`
+    pub(crate) update_date: Option<String>,
 }
 
 impl ArxivMetadata {
@@ -52,6 +91,13 @@ impl ArxivMetadata {
         self.id.as_ref()
     }
 
+    /// the parsed id. Fails if the record has no id or it is in neither numbering scheme.
+    pub fn arxiv_id(&self) -> AppResult<ArxivId> {
+        let id = self.id.as_deref()
+            .ok_or_else(|| AppError::InvalidArxivId(String::new()))?;
+        ArxivId::parse(id)
+    }
+
     pub fn submitter(&self) -> Option<&String> {
         self.submitter.as_ref()
     }
@@ -80,6 +126,19 @@ impl ArxivMetadata {
         self.categories.as_ref()
     }
 
+    /// the individual category codes, primary first
+    pub fn category_list(&self) -> Vec<&str> {
+        self.categories.as_deref()
+            .map(|categories| split_categories(categories).collect())
+            .unwrap_or_default()
+    }
+
+    /// the primary category, which arXiv lists first
+    pub fn primary_category(&self) -> Option<&str> {
+        self.categories.as_deref()
+            .and_then(|categories| split_categories(categories).next())
+    }
+
     pub fn abstract_text(&self) -> Option<&String> {
         self.abstract_text.as_ref()
     }
@@ -87,4 +146,22 @@ impl ArxivMetadata {
     pub fn versions(&self) -> &Vec<ArxivVersion> {
         &self.versions
     }
+
+    pub fn authors_parsed(&self) -> &[ArxivAuthor] {
+        &self.authors_parsed
+    }
+
+    pub fn update_date(&self) -> Option<&String> {
+        self.update_date.as_ref()
+    }
+}
+
+/// splits a space-separated category list, as stored in the kaggle dump, into category codes
+pub fn split_categories(categories: &str) -> impl Iterator<Item=&str> {
+    categories.split_whitespace()
+}
+
+/// formats a timestamp as ISO-8601 in UTC with second precision, which sorts lexicographically
+pub fn to_iso8601_utc<TzT: chrono::TimeZone>(date: &DateTime<TzT>) -> String {
+    date.with_timezone(&Utc).to_rfc3339_opts(SecondsFormat::Secs, true)
 }
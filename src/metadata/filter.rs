@@ -0,0 +1,304 @@
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use regex::Regex;
+use crate::err::{AppError, AppResult};
+use crate::metadata::{split_categories, ArxivMetadata, ArxivVersion};
+
+/// which version of a paper a date range is checked against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum VersionSelector {
+    /// the first submitted version, i.e. the original submission date
+    #[default]
+    First,
+    /// the most recent version
+    Last,
+}
+
+impl VersionSelector {
+    fn select<'a>(&self, versions: &'a [ArxivVersion]) -> Option<&'a ArxivVersion> {
+        match self {
+            VersionSelector::First => versions.first(),
+            VersionSelector::Last => versions.last(),
+        }
+    }
+}
+
+/// a half-open date range `[since, until)`. Either bound may be left open.
+#[derive(Debug, Clone, Default)]
+pub struct DateRange {
+    pub since: Option<DateTime<FixedOffset>>,
+    pub until: Option<DateTime<FixedOffset>>,
+}
+
+impl DateRange {
+    pub fn new(since: Option<DateTime<FixedOffset>>, until: Option<DateTime<FixedOffset>>) -> Self {
+        Self {
+            since,
+            until,
+        }
+    }
+
+    pub fn contains(&self, date: &DateTime<FixedOffset>) -> bool {
+        self.since.is_none_or(|since| date >= &since)
+            && self.until.is_none_or(|until| date < &until)
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.since.is_none() && self.until.is_none()
+    }
+}
+
+/// parses a date bound given either as `YYYY-MM-DD` (midnight UTC) or as a full RFC 3339 timestamp
+pub fn parse_date_bound(value: &str) -> AppResult<DateTime<FixedOffset>> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        let midnight = date.and_hms_opt(0, 0, 0).expect("midnight should always be valid");
+        return Ok(midnight.and_utc().fixed_offset());
+    }
+
+    DateTime::parse_from_rfc3339(value)
+        .map_err(|err| AppError::Other(format!("invalid date '{}': {}", value, err)))
+}
+
+/// a glob over arxiv category codes, such as `cs.*` or `stat.ML`. `*` matches any run of
+/// characters and `?` matches a single character.
+#[derive(Debug, Clone)]
+pub struct CategoryPattern {
+    glob: String,
+    regex: Regex,
+}
+
+impl CategoryPattern {
+    pub fn new<StrT: Into<String>>(glob: StrT) -> AppResult<Self> {
+        let glob = glob.into();
+        let mut pattern = String::from("^");
+        for ch in glob.chars() {
+            match ch {
+                '*' => pattern.push_str(".*"),
+                '?' => pattern.push('.'),
+                other => pattern.push_str(&regex::escape(&other.to_string())),
+            }
+        }
+        pattern.push('$');
+
+        let regex = Regex::new(&pattern)
+            .map_err(|err| AppError::Other(format!("invalid category pattern '{}': {}", glob, err)))?;
+
+        Ok(Self {
+            glob,
+            regex,
+        })
+    }
+
+    pub fn glob(&self) -> &str {
+        &self.glob
+    }
+
+    pub fn is_match(&self, category: &str) -> bool {
+        self.regex.is_match(category)
+    }
+}
+
+/// Decides which metadata records should be loaded into the database.
+///
+/// A record passes when its selected version falls within the date range, none of its considered
+/// categories match an exclude pattern, and at least one of them matches an include pattern. An
+/// empty include list accepts every category. In primary-only mode, only the first listed category
+/// is considered.
+#[derive(Debug, Clone, Default)]
+pub struct MetadataFilter {
+    date_range: DateRange,
+    version: VersionSelector,
+    include: Vec<CategoryPattern>,
+    exclude: Vec<CategoryPattern>,
+    primary_only: bool,
+}
+
+impl MetadataFilter {
+    /// creates a filter that accepts everything
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_date_range(mut self, date_range: DateRange, version: VersionSelector) -> Self {
+        self.date_range = date_range;
+        self.version = version;
+        self
+    }
+
+    pub fn with_include(mut self, patterns: Vec<CategoryPattern>) -> Self {
+        self.include = patterns;
+        self
+    }
+
+    pub fn with_exclude(mut self, patterns: Vec<CategoryPattern>) -> Self {
+        self.exclude = patterns;
+        self
+    }
+
+    pub fn with_primary_only(mut self, primary_only: bool) -> Self {
+        self.primary_only = primary_only;
+        self
+    }
+
+    pub fn matches(&self, metadata: &ArxivMetadata) -> bool {
+        self.matches_date(metadata.versions())
+            && metadata.categories().is_some_and(|categories| self.matches_categories(categories))
+    }
+
+    pub fn matches_date(&self, versions: &[ArxivVersion]) -> bool {
+        if self.date_range.is_unbounded() {
+            return true;
+        }
+
+        self.version.select(versions)
+            .and_then(|version| version.created_date().ok())
+            .is_some_and(|created| self.date_range.contains(&created))
+    }
+
+    /// checks a space-separated category list, as stored in the kaggle dump
+    pub fn matches_categories(&self, categories: &str) -> bool {
+        let mut considered = split_categories(categories);
+        let considered: Vec<&str> = if self.primary_only {
+            considered.next().into_iter().collect()
+        } else {
+            considered.collect()
+        };
+
+        if considered.is_empty() {
+            return false;
+        }
+
+        let excluded = considered.iter()
+            .any(|category| self.exclude.iter().any(|pattern| pattern.is_match(category)));
+        if excluded {
+            return false;
+        }
+
+        self.include.is_empty() || considered.iter()
+            .any(|category| self.include.iter().any(|pattern| pattern.is_match(category)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(globs: &[&str]) -> Vec<CategoryPattern> {
+        globs.iter()
+            .map(|glob| CategoryPattern::new(*glob).unwrap())
+            .collect()
+    }
+
+    fn metadata(categories: &str, created: &[&str]) -> ArxivMetadata {
+        let versions = created.iter()
+            .enumerate()
+            .map(|(idx, created)| serde_json::json!({ "version": format!("v{}", idx + 1), "created": created }))
+            .collect::<Vec<_>>();
+        serde_json::from_value(serde_json::json!({
+            "id": "2101.00001",
+            "categories": categories,
+            "versions": versions,
+        })).unwrap()
+    }
+
+    fn date(value: &str) -> DateTime<FixedOffset> {
+        parse_date_bound(value).unwrap()
+    }
+
+    #[test]
+    fn category_glob_matching() {
+        let any_cs = CategoryPattern::new("cs.*").unwrap();
+        assert!(any_cs.is_match("cs.LG"));
+        assert!(any_cs.is_match("cs."));
+        assert!(!any_cs.is_match("econ.cs"));
+        assert!(!any_cs.is_match("csx.LG"));
+
+        let exact = CategoryPattern::new("stat.ML").unwrap();
+        assert!(exact.is_match("stat.ML"));
+        assert!(!exact.is_match("stat.MLx"));
+        assert!(!exact.is_match("statxML"));
+
+        let single = CategoryPattern::new("cs.?L").unwrap();
+        assert!(single.is_match("cs.CL"));
+        assert!(!single.is_match("cs.L"));
+        assert!(!single.is_match("cs.CCL"));
+    }
+
+    #[test]
+    fn empty_include_accepts_everything() {
+        let filter = MetadataFilter::new();
+        assert!(filter.matches_categories("math.CO hep-th"));
+        assert!(!filter.matches_categories(""));
+    }
+
+    #[test]
+    fn primary_only_considers_first_category() {
+        let filter = MetadataFilter::new().with_include(patterns(&["cs.*"]));
+        assert!(filter.matches_categories("stat.ML cs.LG"));
+
+        let filter = filter.with_primary_only(true);
+        assert!(!filter.matches_categories("stat.ML cs.LG"));
+        assert!(filter.matches_categories("cs.LG stat.ML"));
+    }
+
+    #[test]
+    fn exclude_wins_over_include() {
+        let filter = MetadataFilter::new()
+            .with_include(patterns(&["cs.*", "stat.ML"]))
+            .with_exclude(patterns(&["cs.CR"]));
+        assert!(filter.matches_categories("cs.LG stat.ML"));
+        assert!(!filter.matches_categories("cs.LG cs.CR"));
+        assert!(!filter.matches_categories("cs.CR"));
+
+        // in primary-only mode, an excluded secondary category no longer counts
+        let filter = filter.with_primary_only(true);
+        assert!(filter.matches_categories("cs.LG cs.CR"));
+    }
+
+    #[test]
+    fn date_range_is_half_open() {
+        let range = DateRange::new(Some(date("2020-01-01")), Some(date("2021-01-01")));
+        assert!(range.contains(&date("2020-01-01T00:00:00Z")));
+        assert!(range.contains(&date("2020-12-31T23:59:59Z")));
+        assert!(!range.contains(&date("2021-01-01T00:00:00Z")));
+        assert!(!range.contains(&date("2019-12-31T23:59:59Z")));
+
+        let since = DateRange::new(Some(date("2020-01-01")), None);
+        assert!(since.contains(&date("2099-01-01")));
+        assert!(!since.contains(&date("2019-01-01")));
+
+        let until = DateRange::new(None, Some(date("2020-01-01")));
+        assert!(until.contains(&date("1991-08-14")));
+        assert!(!until.contains(&date("2020-01-01")));
+
+        assert!(DateRange::default().is_unbounded());
+    }
+
+    #[test]
+    fn date_bounds_accept_dates_and_timestamps() {
+        assert_eq!(date("2020-01-01"), date("2020-01-01T00:00:00Z"));
+        assert_eq!(date("2020-01-01T06:00:00+06:00"), date("2020-01-01"));
+        assert!(parse_date_bound("01/01/2020").is_err());
+    }
+
+    #[test]
+    fn first_or_last_version_selects_the_date() {
+        let paper = metadata("cs.LG", &["Mon, 2 Dec 2019 10:00:00 GMT", "Tue, 7 Jan 2020 10:00:00 GMT"]);
+        let range = DateRange::new(Some(date("2020-01-01")), None);
+
+        let first = MetadataFilter::new().with_date_range(range.clone(), VersionSelector::First);
+        assert!(!first.matches(&paper));
+
+        let last = MetadataFilter::new().with_date_range(range, VersionSelector::Last);
+        assert!(last.matches(&paper));
+    }
+
+    #[test]
+    fn bounded_range_rejects_papers_without_versions() {
+        let paper = metadata("cs.LG", &[]);
+        assert!(MetadataFilter::new().matches(&paper));
+
+        let bounded = MetadataFilter::new()
+            .with_date_range(DateRange::new(Some(date("2020-01-01")), None), VersionSelector::First);
+        assert!(!bounded.matches(&paper));
+    }
+}
@@ -0,0 +1,240 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::err::AppError;
+
+/// An arXiv identifier in either numbering scheme, with an optional version suffix.
+///
+/// - new scheme (April 2007 on): `YYMM.NNNN` or, from 2015, `YYMM.NNNNN`, e.g. `2101.00001v2`
+/// - legacy scheme: `archive[.SC]/YYMMNNN`, e.g. `cs/0601001` or `math.GT/0309136v1`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArxivId {
+    /// archive for legacy ids, such as `math` or `hep-th`. `None` for new-scheme ids.
+    archive: Option<String>,
+    /// optional subject class of a legacy id, such as `GT` in `math.GT/0309136`
+    subject_class: Option<String>,
+    yymm: String,
+    /// sequence number within the month. For legacy ids this is the trailing three digits.
+    number: String,
+    version: Option<u32>,
+}
+
+impl ArxivId {
+    pub fn parse(value: &str) -> Result<Self, AppError> {
+        let invalid = || AppError::InvalidArxivId(value.to_string());
+        let trimmed = value.trim();
+        let trimmed = trimmed.strip_prefix("arXiv:").unwrap_or(trimmed);
+        let (base, version) = split_version(trimmed).ok_or_else(invalid)?;
+
+        if let Some((archive_part, digits)) = base.split_once('/') {
+            let (archive, subject_class) = match archive_part.split_once('.') {
+                Some((archive, subject_class)) => (archive, Some(subject_class)),
+                None => (archive_part, None),
+            };
+
+            let valid_archive = !archive.is_empty()
+                && archive.chars().all(|ch| ch.is_ascii_lowercase() || ch == '-');
+            let valid_subject = subject_class
+                .is_none_or(|sc| !sc.is_empty() && sc.chars().all(|ch| ch.is_ascii_alphabetic() || ch == '-'));
+            if !valid_archive || !valid_subject || digits.len() != 7 || !is_digits(digits) {
+                return Err(invalid());
+            }
+
+            return Ok(Self {
+                archive: Some(archive.to_string()),
+                subject_class: subject_class.map(str::to_string),
+                yymm: digits[..4].to_string(),
+                number: digits[4..].to_string(),
+                version,
+            });
+        }
+
+        let (yymm, number) = base.split_once('.').ok_or_else(invalid)?;
+        if yymm.len() != 4 || !is_digits(yymm) || !(4..=5).contains(&number.len()) || !is_digits(number) {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            archive: None,
+            subject_class: None,
+            yymm: yymm.to_string(),
+            number: number.to_string(),
+            version,
+        })
+    }
+
+    pub fn is_legacy(&self) -> bool {
+        self.archive.is_some()
+    }
+
+    pub fn archive(&self) -> Option<&str> {
+        self.archive.as_deref()
+    }
+
+    pub fn subject_class(&self) -> Option<&str> {
+        self.subject_class.as_deref()
+    }
+
+    /// year and month of submission as `YYMM`
+    pub fn yymm(&self) -> &str {
+        &self.yymm
+    }
+
+    pub fn number(&self) -> &str {
+        &self.number
+    }
+
+    pub fn version(&self) -> Option<u32> {
+        self.version
+    }
+
+    pub fn with_version(&self, version: Option<u32>) -> Self {
+        Self {
+            version,
+            ..self.clone()
+        }
+    }
+
+    /// the id without a version suffix, as stored in the metadata tables
+    pub fn base_id(&self) -> String {
+        match self.archive.as_deref() {
+            Some(archive) => {
+                let subject_class = self.subject_class.as_deref()
+                    .map(|sc| format!(".{}", sc))
+                    .unwrap_or_default();
+                format!("{}{}/{}{}", archive, subject_class, self.yymm, self.number)
+            }
+            None => format!("{}.{}", self.yymm, self.number),
+        }
+    }
+
+    /// the file stem used by arXiv's PDF storage: `YYMM.NNNNN` or, for legacy ids, `YYMMNNN`
+    pub fn file_stem(&self) -> String {
+        if self.is_legacy() {
+            format!("{}{}", self.yymm, self.number)
+        } else {
+            format!("{}.{}", self.yymm, self.number)
+        }
+    }
+}
+
+impl Display for ArxivId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.version {
+            Some(version) => write!(f, "{}v{}", self.base_id(), version),
+            None => write!(f, "{}", self.base_id()),
+        }
+    }
+}
+
+impl FromStr for ArxivId {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// splits off a trailing `vN`. Returns `None` if a `v` suffix is present but malformed.
+fn split_version(value: &str) -> Option<(&str, Option<u32>)> {
+    let Some(idx) = value.rfind('v') else {
+        return Some((value, None));
+    };
+
+    let digits = &value[idx + 1..];
+    // a `v` before the final path segment belongs to the archive, e.g. `solv-int/9901001`
+    if digits.contains('/') {
+        return Some((value, None));
+    }
+
+    if digits.is_empty() || !is_digits(digits) {
+        return None;
+    }
+
+    digits.parse().ok().map(|version| (&value[..idx], Some(version)))
+}
+
+fn is_digits(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(value: &str) -> ArxivId {
+        ArxivId::parse(value).unwrap()
+    }
+
+    #[test]
+    fn parses_new_scheme_ids() {
+        let id = parse("2101.00001v2");
+        assert!(!id.is_legacy());
+        assert_eq!(id.yymm(), "2101");
+        assert_eq!(id.number(), "00001");
+        assert_eq!(id.version(), Some(2));
+        assert_eq!(id.base_id(), "2101.00001");
+        assert_eq!(id.file_stem(), "2101.00001");
+        assert_eq!(id.to_string(), "2101.00001v2");
+
+        // four digit numbers were used until the end of 2014
+        let id = parse("0704.0001");
+        assert_eq!(id.number(), "0001");
+        assert_eq!(id.version(), None);
+        assert_eq!(id.to_string(), "0704.0001");
+
+        assert_eq!(parse(" arXiv:1501.00001v10 ").to_string(), "1501.00001v10");
+    }
+
+    #[test]
+    fn parses_legacy_ids() {
+        let id = parse("cs/0601001");
+        assert!(id.is_legacy());
+        assert_eq!(id.archive(), Some("cs"));
+        assert_eq!(id.subject_class(), None);
+        assert_eq!(id.yymm(), "0601");
+        assert_eq!(id.number(), "001");
+        assert_eq!(id.file_stem(), "0601001");
+        assert_eq!(id.base_id(), "cs/0601001");
+
+        let id = parse("math.GT/0309136v1");
+        assert_eq!(id.archive(), Some("math"));
+        assert_eq!(id.subject_class(), Some("GT"));
+        assert_eq!(id.version(), Some(1));
+        assert_eq!(id.base_id(), "math.GT/0309136");
+        assert_eq!(id.to_string(), "math.GT/0309136v1");
+
+        // archives may contain a `v` and a dash without it being read as a version
+        let id = parse("solv-int/9901001");
+        assert_eq!(id.archive(), Some("solv-int"));
+        assert_eq!(id.version(), None);
+        assert_eq!(parse("hep-th/9901001v3").version(), Some(3));
+    }
+
+    #[test]
+    fn rejects_malformed_ids() {
+        for value in [
+            "",
+            "2101",
+            "2101.001",
+            "2101.000001",
+            "21a1.00001",
+            "2101.00001v",
+            "2101.00001vx",
+            "cs/060100",
+            "cs/06010011",
+            "CS/0601001",
+            "math./0309136",
+            "/0601001",
+        ] {
+            assert!(ArxivId::parse(value).is_err(), "{:?} should not parse", value);
+        }
+    }
+
+    #[test]
+    fn version_can_be_replaced() {
+        let id = parse("2101.00001v2");
+        assert_eq!(id.with_version(None).to_string(), "2101.00001");
+        assert_eq!(id.with_version(Some(5)).to_string(), "2101.00001v5");
+        assert_eq!("cs/0601001v2".parse::<ArxivId>().unwrap(), parse("cs/0601001").with_version(Some(2)));
+    }
+}
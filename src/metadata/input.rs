@@ -0,0 +1,226 @@
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use log::info;
+use xz2::read::XzDecoder;
+use crate::err::AppResult;
+
+/// path value that means "read from STDIN"
+pub const STDIN_PATH: &str = "-";
+
+/// compression formats recognized for metadata dumps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /// detects the compression format from the leading magic bytes of a stream
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if header.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Compression::Xz
+        } else {
+            Compression::None
+        }
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Compression::None => "uncompressed",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+            Compression::Xz => "xz",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+/// A decompressed metadata stream that keeps count of the raw (possibly compressed) bytes consumed,
+/// so progress can be reported against the size of the file on disk.
+pub struct MetadataInput {
+    reader: Box<dyn Read + Send>,
+    total_bytes: Option<u64>,
+    bytes_read: Arc<AtomicU64>,
+}
+
+impl MetadataInput {
+    /// size of the raw input, if known. Unknown for STDIN.
+    pub fn total_bytes(&self) -> Option<u64> {
+        self.total_bytes
+    }
+
+    /// shared counter of raw bytes read so far, readable from other threads
+    pub fn bytes_read(&self) -> Arc<AtomicU64> {
+        self.bytes_read.clone()
+    }
+}
+
+impl Read for MetadataInput {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.reader.read(buf)
+    }
+}
+
+/// opens a metadata dump for streaming. `-` reads from STDIN. Compressed input is detected from
+/// magic bytes and decompressed on the fly.
+pub fn open_metadata_input<PathT: AsRef<Path>>(path: PathT) -> AppResult<MetadataInput> {
+    let path = path.as_ref();
+    let bytes_read = Arc::new(AtomicU64::new(0));
+    let (reader, total_bytes) = if path.as_os_str() == STDIN_PATH {
+        info!("reading metadata from stdin");
+        let reader = CountingReader::new(std::io::stdin(), bytes_read.clone());
+        (decompressing_reader(reader)?, None)
+    } else {
+        info!("reading metadata from {}", path.display());
+        let file = File::open(path)?;
+        let total_bytes = file.metadata()?.len();
+        let reader = CountingReader::new(file, bytes_read.clone());
+        (decompressing_reader(reader)?, Some(total_bytes))
+    };
+
+    Ok(MetadataInput {
+        reader,
+        total_bytes,
+        bytes_read,
+    })
+}
+
+struct CountingReader<ReaderT: Read> {
+    inner: ReaderT,
+    bytes_read: Arc<AtomicU64>,
+}
+
+impl<ReaderT: Read> CountingReader<ReaderT> {
+    fn new(inner: ReaderT, bytes_read: Arc<AtomicU64>) -> Self {
+        Self {
+            inner,
+            bytes_read,
+        }
+    }
+}
+
+impl<ReaderT: Read> Read for CountingReader<ReaderT> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.bytes_read.fetch_add(len as u64, Ordering::Relaxed);
+        Ok(len)
+    }
+}
+
+/// wraps the given reader in the decoder matching its magic bytes
+pub fn decompressing_reader<ReaderT: 'static + Read + Send>(mut reader: ReaderT) -> AppResult<Box<dyn Read + Send>> {
+    let header = read_header(&mut reader)?;
+    let compression = Compression::detect(&header);
+    info!("detected {} metadata input", compression);
+
+    // put the sniffed bytes back in front of the rest of the stream
+    let reader = BufReader::new(Cursor::new(header).chain(reader));
+    let decoded: Box<dyn Read + Send> = match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?),
+        Compression::Bzip2 => Box::new(MultiBzDecoder::new(reader)),
+        Compression::Xz => Box::new(XzDecoder::new_multi_decoder(reader)),
+    };
+
+    Ok(decoded)
+}
+
+/// reads up to the longest magic number, tolerating short reads from pipes
+fn read_header<ReaderT: Read>(reader: &mut ReaderT) -> AppResult<Vec<u8>> {
+    const MAGIC_LEN: u64 = 6;
+    let mut header = Vec::with_capacity(MAGIC_LEN as usize);
+    reader.take(MAGIC_LEN).read_to_end(&mut header)?;
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use bzip2::write::BzEncoder;
+    use flate2::write::GzEncoder;
+    use xz2::write::XzEncoder;
+
+    const NDJSON: &str = "{\"id\": \"2101.00001\"}\n{\"id\": \"2101.00002\"}\n";
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn encode(compression: Compression, bytes: &[u8]) -> Vec<u8> {
+        match compression {
+            Compression::None => bytes.to_vec(),
+            Compression::Gzip => gzip(bytes),
+            Compression::Zstd => zstd::encode_all(bytes, 3).unwrap(),
+            Compression::Bzip2 => {
+                let mut encoder = BzEncoder::new(Vec::new(), bzip2::Compression::default());
+                encoder.write_all(bytes).unwrap();
+                encoder.finish().unwrap()
+            }
+            Compression::Xz => {
+                let mut encoder = XzEncoder::new(Vec::new(), 6);
+                encoder.write_all(bytes).unwrap();
+                encoder.finish().unwrap()
+            }
+        }
+    }
+
+    /// writes `encoded` to a file and reads it back through [open_metadata_input]
+    fn read_back(encoded: &[u8]) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metadata.json");
+        std::fs::write(&path, encoded).unwrap();
+
+        let mut input = open_metadata_input(&path).unwrap();
+        assert_eq!(input.total_bytes(), Some(encoded.len() as u64));
+        let mut decoded = String::new();
+        input.read_to_string(&mut decoded).unwrap();
+        assert_eq!(input.bytes_read().load(Ordering::Relaxed), encoded.len() as u64);
+        decoded
+    }
+
+    #[test]
+    fn round_trips_every_compression() {
+        for compression in [Compression::None, Compression::Gzip, Compression::Zstd, Compression::Bzip2, Compression::Xz] {
+            let encoded = encode(compression, NDJSON.as_bytes());
+            assert_eq!(Compression::detect(&encoded), compression);
+            assert_eq!(read_back(&encoded), NDJSON, "{} input", compression);
+        }
+    }
+
+    #[test]
+    fn reads_every_gzip_member() {
+        // what `cat a.json.gz b.json.gz` produces
+        let (first, second) = NDJSON.split_at(NDJSON.find('\n').unwrap() + 1);
+        let encoded = [gzip(first.as_bytes()), gzip(second.as_bytes())].concat();
+        assert_eq!(read_back(&encoded), NDJSON);
+    }
+
+    #[test]
+    fn reads_input_shorter_than_magic() {
+        assert_eq!(read_back(b"{}\n"), "{}\n");
+        assert_eq!(read_back(b""), "");
+    }
+}
@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::{Arc, Mutex};
+use log::debug;
+use crate::err::AppResult;
+use crate::metadata::filter::MetadataFilter;
+use crate::metadata::reader::{ArxivMetadataIter, RawMetadataLine};
+use crate::metadata::ArxivMetadata;
+
+/// a run of consecutive lines from the dump, parsed and filtered
+pub struct ParsedChunk {
+    /// records that passed the filter and lines that failed to parse, in input order
+    pub records: Vec<AppResult<ArxivMetadata>>,
+    /// how many well-formed records the filter rejected
+    pub filtered_out: u64,
+    /// how many lines the chunk covered
+    pub lines: u64,
+}
+
+/// Parses a metadata dump on several threads.
+///
+/// A reader thread splits the input into chunks of lines, worker threads parse and filter them,
+/// and the calling thread receives the chunks back in input order. Channels are bounded, so memory
+/// stays flat no matter how far the consumer falls behind.
+pub struct ParallelMetadataReader {
+    workers: usize,
+    chunk_lines: usize,
+}
+
+impl ParallelMetadataReader {
+    pub fn new(workers: usize) -> Self {
+        Self {
+            workers: workers.max(1),
+            chunk_lines: 1000,
+        }
+    }
+
+    pub fn with_chunk_lines(mut self, chunk_lines: usize) -> Self {
+        self.chunk_lines = chunk_lines.max(1);
+        self
+    }
+
+    /// runs the pipeline, handing each chunk to `consume` on the calling thread. An error from
+    /// `consume` or a read error stops the pipeline and is returned.
+    pub fn for_each_chunk<ReaderT, ConsumerT>(&self, reader: ReaderT, filter: &MetadataFilter, mut consume: ConsumerT) -> AppResult<()>
+    where
+        ReaderT: Read + Send,
+        ConsumerT: FnMut(ParsedChunk) -> AppResult<()>,
+    {
+        let (raw_sender, raw_receiver) = sync_channel::<(u64, Vec<RawMetadataLine>)>(self.workers * 2);
+        let (parsed_sender, parsed_receiver) = sync_channel::<(u64, ParsedChunk)>(self.workers * 2);
+        let raw_receiver = Arc::new(Mutex::new(raw_receiver));
+        let chunk_lines = self.chunk_lines;
+
+        std::thread::scope(|scope| {
+            let reader_handle = scope.spawn(move || -> AppResult<u64> {
+                let mut lines = ArxivMetadataIter::new(reader);
+                let mut sequence = 0u64;
+                loop {
+                    let mut chunk = Vec::with_capacity(chunk_lines);
+                    while chunk.len() < chunk_lines {
+                        match lines.next_raw_line()? {
+                            Some(line) => chunk.push(line),
+                            None => break,
+                        }
+                    }
+
+                    if chunk.is_empty() {
+                        break;
+                    }
+
+                    // the consumer hung up, so stop reading
+                    if raw_sender.send((sequence, chunk)).is_err() {
+                        break;
+                    }
+                    sequence += 1;
+                }
+
+                debug!("metadata reader finished after {} chunk(s)", sequence);
+                Ok(sequence)
+            });
+
+            for _ in 0..self.workers {
+                let raw_receiver = raw_receiver.clone();
+                let parsed_sender = parsed_sender.clone();
+                scope.spawn(move || {
+                    while let Some((sequence, lines)) = next_chunk(&raw_receiver) {
+                        let chunk = parse_chunk(lines, filter);
+                        if parsed_sender.send((sequence, chunk)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(parsed_sender);
+            drop(raw_receiver);
+
+            // hand chunks to the consumer in input order
+            let consumed = (|| {
+                let mut pending = BTreeMap::new();
+                let mut next_sequence = 0u64;
+                for (sequence, chunk) in parsed_receiver.iter() {
+                    pending.insert(sequence, chunk);
+                    while let Some(chunk) = pending.remove(&next_sequence) {
+                        consume(chunk)?;
+                        next_sequence += 1;
+                    }
+                }
+
+                Ok(())
+            })();
+
+            // unblock the reader and workers if we stopped early
+            drop(parsed_receiver);
+            let read_result = reader_handle.join().expect("metadata reader thread panicked");
+
+            consumed.and(read_result.map(|_| ()))
+        })
+    }
+}
+
+fn next_chunk(receiver: &Mutex<Receiver<(u64, Vec<RawMetadataLine>)>>) -> Option<(u64, Vec<RawMetadataLine>)> {
+    receiver.lock()
+        .expect("chunk receiver lock poisoned")
+        .recv()
+        .ok()
+}
+
+fn parse_chunk(lines: Vec<RawMetadataLine>, filter: &MetadataFilter) -> ParsedChunk {
+    let line_count = lines.len() as u64;
+    let mut filtered_out = 0u64;
+    let records = lines.iter()
+        .filter_map(RawMetadataLine::parse)
+        .filter(|record| match record {
+            Ok(metadata) if !filter.matches(metadata) => {
+                filtered_out += 1;
+                false
+            }
+            _ => true,
+        })
+        .collect();
+
+    ParsedChunk {
+        records,
+        filtered_out,
+        lines: line_count,
+    }
+}
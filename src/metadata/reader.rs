@@ -1,45 +1,168 @@
 use std::io::{BufRead, BufReader, Read};
-use crate::err::AppResult;
+use crate::err::{AppError, AppResult};
 use crate::metadata::ArxivMetadata;
 
+/// one unparsed line of a metadata dump, with its position for diagnostics
+pub struct RawMetadataLine {
+    /// 1-based line number
+    pub line: u64,
+    /// byte offset of the start of the line in the (decompressed) input
+    pub offset: u64,
+    pub bytes: Vec<u8>,
+}
+
+impl RawMetadataLine {
+    /// parses the line. Returns `None` for blank lines, e.g. a trailing newline at the end of the
+    /// dump.
+    pub fn parse(&self) -> Option<AppResult<ArxivMetadata>> {
+        if self.bytes.iter().all(u8::is_ascii_whitespace) {
+            return None;
+        }
+
+        let result = serde_json::from_slice(&self.bytes)
+            .map_err(|err| AppError::InvalidMetadataRecord {
+                line: self.line,
+                offset: self.offset,
+                raw: String::from_utf8_lossy(&self.bytes).trim_end().to_string(),
+                source: err,
+            });
+
+        Some(result)
+    }
+}
+
+/// Streams metadata records out of a newline-delimited JSON dump.
+///
+/// Malformed lines are yielded as [AppError::InvalidMetadataRecord] and iteration continues with
+/// the next line. I/O errors are yielded once and end the iteration.
 pub struct ArxivMetadataIter<T: Read> {
     reader: BufReader<T>,
+    /// 1-based number of the last line read
+    line_number: u64,
+    /// total bytes consumed so far
+    bytes_read: u64,
+    finished: bool,
 }
 
 impl<T: Read> ArxivMetadataIter<T> {
     pub fn new(reader: T) -> Self {
         Self {
             reader: BufReader::new(reader),
+            line_number: 0,
+            bytes_read: 0,
+            finished: false,
         }
     }
 
-    fn read_next(&mut self) -> AppResult<Option<ArxivMetadata>> {
-        let ok_res = loop {
-            let mut buffer = String::new();
-            match self.reader.read_line(&mut buffer) {
-                Ok(0) => break None,
-                Ok(_) => {},
-                Err(err) => return Err(err.into()),
-            }
+    /// line number of the most recently read record
+    pub fn line_number(&self) -> u64 {
+        self.line_number
+    }
+
+    /// how many bytes of (decompressed) input have been consumed
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// reads the next line without parsing it. Returns `None` at the end of the input.
+    pub fn next_raw_line(&mut self) -> AppResult<Option<RawMetadataLine>> {
+        let mut bytes = Vec::<u8>::new();
+        let offset = self.bytes_read;
+        let len = self.reader.read_until(b'\n', &mut bytes)?;
+        if len == 0 {
+            return Ok(None);
+        }
 
-            match serde_json::from_str(&buffer) {
-                Ok(model) => break Some(model),
-                Err(err) => return Err(err.into())
+        self.line_number += 1;
+        self.bytes_read += len as u64;
+
+        Ok(Some(RawMetadataLine {
+            line: self.line_number,
+            offset,
+            bytes,
+        }))
+    }
+
+    fn read_next(&mut self) -> AppResult<Option<ArxivMetadata>> {
+        while let Some(raw_line) = self.next_raw_line()? {
+            if let Some(result) = raw_line.parse() {
+                return result.map(Some);
             }
-        };
+        }
 
-        Ok(ok_res)
+        Ok(None)
     }
 }
 
 impl<T: Read> Iterator for ArxivMetadataIter<T> {
-    type Item = ArxivMetadata;
+    type Item = AppResult<ArxivMetadata>;
 
     fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
         match self.read_next() {
-            Ok(None) | Err(_) => None,
-            Ok(Some(value)) => Some(value)
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Ok(Some(value)) => Some(Ok(value)),
+            Err(err @ AppError::InvalidMetadataRecord { .. }) => Some(Err(err)),
+            Err(err) => {
+                self.finished = true;
+                Some(Err(err))
+            }
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const VALID: &str = r#"{"id": "2101.00001", "abstract": "An abstract.", "categories": "cs.LG", "versions": []}"#;
+    const MALFORMED: &str = r#"{"id": "2101.00002", "abstract": "An abs"#;
+
+    fn arxiv_id(result: Option<AppResult<ArxivMetadata>>) -> String {
+        result.unwrap().unwrap().arxiv_id().unwrap().base_id()
+    }
+
+    #[test]
+    fn reports_malformed_lines_and_continues() {
+        let input = format!("{}\n{}\n\n{}\n", VALID, MALFORMED, VALID.replace("00001", "00003"));
+        let mut records = ArxivMetadataIter::new(Cursor::new(input.clone()));
+
+        assert_eq!(arxiv_id(records.next()), "2101.00001");
+        match records.next() {
+            Some(Err(AppError::InvalidMetadataRecord { line, offset, raw, .. })) => {
+                assert_eq!(line, 2);
+                assert_eq!(offset, VALID.len() as u64 + 1);
+                assert_eq!(raw, MALFORMED);
+            }
+            Some(Err(err)) => panic!("unexpected error: {}", err),
+            _ => panic!("expected an invalid record"),
+        }
+        // the blank line is skipped
+        assert_eq!(arxiv_id(records.next()), "2101.00003");
+        assert_eq!(records.line_number(), 4);
+        assert!(records.next().is_none());
+        assert_eq!(records.bytes_read(), input.len() as u64);
+    }
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("disk on fire"))
+        }
+    }
+
+    #[test]
+    fn stops_after_io_error() {
+        let mut records = ArxivMetadataIter::new(FailingReader);
+        assert!(matches!(records.next(), Some(Err(AppError::IO(_)))));
+        assert!(records.next().is_none());
+    }
+}
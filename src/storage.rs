@@ -0,0 +1,147 @@
+pub mod memory;
+mod sqlite;
+
+use std::time::Duration;
+use crate::content::keyword::KeywordStrategy;
+use crate::content::{ArxivPaperContent, ArxivPaperContentEntity, PaperText};
+use crate::db::pages::{PageCursor, QueryPage};
+use crate::err::AppResult;
+use crate::extraction::ExtractError;
+use crate::metadata::id::ArxivId;
+use crate::metadata::ArxivMetadata;
+
+/// Where papers, extracted content and extraction results are kept.
+///
+/// [crate::db::ArxivDBQueries] implements this on top of SQLite, and [memory::MemoryPaperStore]
+/// keeps everything in memory, so extraction pipelines can run without a database file.
+pub trait PaperStore {
+    /// adds a new paper. Fails if the paper is already stored.
+    fn insert_arxiv_metadata(&self, metadata: ArxivMetadata) -> AppResult<()>;
+
+    /// saves the extracted keywords, body and provenance of a paper, replacing earlier ones
+    fn upsert_content(&self, content: ArxivPaperContent) -> AppResult<()>;
+
+    /// keeps a paper's full text for offline re-extraction, replacing text stored for the same
+    /// version
+    fn insert_paper_text(&self, text: &PaperText) -> AppResult<()>;
+
+    /// the stored text of the latest stored version of a paper
+    fn select_paper_text(&self, arxiv_id: &str) -> AppResult<Option<PaperText>>;
+
+    fn count_papers_with_text(&self) -> AppResult<u64>;
+
+    /// one keyset page of ids of papers with stored text
+    fn select_arxiv_ids_with_text(&self, page: &QueryPage) -> AppResult<Vec<ArxivId>>;
+
+    /// records the outcome of an extraction attempt that took `duration`. Earlier attempts are
+    /// kept, and the latest one is the paper's status. On failure the id is taken from the error.
+    fn insert_extraction_result(&self, id: &str, err: Option<ExtractError>, duration: Duration) -> AppResult<()>;
+
+    /// counts papers whose latest extraction isn't `OK`, including never attempted ones, leaving
+    /// out those already attempted `max_attempts` times
+    fn count_pending_arxiv_ids(&self, max_attempts: Option<u32>) -> AppResult<u64>;
+
+    /// one keyset page of the papers [Self::count_pending_arxiv_ids] counts
+    fn select_pending_arxiv_ids(&self, max_attempts: Option<u32>, page: &QueryPage) -> AppResult<Vec<ArxivId>>;
+
+    /// counts papers whose latest extraction status is one of `statuses`, leaving out those
+    /// already attempted `max_attempts` times
+    fn count_retry_candidates(&self, statuses: &[String], max_attempts: Option<u32>) -> AppResult<u64>;
+
+    /// one keyset page of the papers [Self::count_retry_candidates] counts
+    fn select_retry_candidates(&self, statuses: &[String], max_attempts: Option<u32>, page: &QueryPage) -> AppResult<Vec<ArxivId>>;
+
+    /// counts papers whose keywords were found by `strategy`, leaving out extractions made by
+    /// `current_version` if given
+    fn count_arxiv_ids_by_keyword_strategy(&self, strategy: KeywordStrategy, current_version: Option<&str>) -> AppResult<u64>;
+
+    /// one keyset page of the papers [Self::count_arxiv_ids_by_keyword_strategy] counts
+    fn select_arxiv_ids_by_keyword_strategy(&self, strategy: KeywordStrategy, current_version: Option<&str>, page: &QueryPage) -> AppResult<Vec<ArxivId>>;
+
+    fn count_arxiv_ids(&self) -> AppResult<u64>;
+
+    /// one keyset page of arxiv ids, in id order
+    fn select_arxiv_ids(&self, page: &QueryPage) -> AppResult<Vec<ArxivId>>;
+
+    /// a random sample of papers, optionally only ones without an extraction result
+    fn sample_arxiv_ids(&self, count: u64, unprocessed_only: bool) -> AppResult<Vec<ArxivId>>;
+
+    /// counts training records, optionally only those matching a full-text query
+    fn count_training_data(&self, search: Option<&str>) -> AppResult<u64>;
+
+    /// one keyset page of training records, ordered by arxiv id
+    fn select_training_data(&self, page: &QueryPage, search: Option<&str>) -> AppResult<Vec<ArxivPaperContentEntity>>;
+
+    /// runs `body` atomically where the store supports it. The default just runs it.
+    fn batch<T, BodyT>(&self, body: BodyT) -> AppResult<T>
+    where
+        Self: Sized,
+        BodyT: FnOnce(&Self) -> AppResult<T>,
+    {
+        body(self)
+    }
+
+    /// streams every arxiv id, a page at a time
+    fn arxiv_id_pages(&self, page_size: u64) -> impl Iterator<Item=AppResult<Vec<ArxivId>>>
+    where
+        Self: Sized,
+    {
+        PageCursor::new(page_size, move |page| self.select_arxiv_ids(page), ArxivId::base_id)
+    }
+
+    /// streams the ids of papers with stored text, a page at a time
+    fn arxiv_id_with_text_pages(&self, page_size: u64) -> impl Iterator<Item=AppResult<Vec<ArxivId>>>
+    where
+        Self: Sized,
+    {
+        PageCursor::new(page_size, move |page| self.select_arxiv_ids_with_text(page), ArxivId::base_id)
+    }
+
+    /// streams the papers not yet extracted successfully, a page at a time
+    fn pending_arxiv_id_pages(&self, page_size: u64, max_attempts: Option<u32>) -> impl Iterator<Item=AppResult<Vec<ArxivId>>>
+    where
+        Self: Sized,
+    {
+        PageCursor::new(
+            page_size,
+            move |page| self.select_pending_arxiv_ids(max_attempts, page),
+            ArxivId::base_id,
+        )
+    }
+
+    /// streams the papers due for a retry, a page at a time
+    fn retry_candidate_pages<'a>(&'a self, page_size: u64, statuses: &'a [String], max_attempts: Option<u32>) -> impl Iterator<Item=AppResult<Vec<ArxivId>>> + 'a
+    where
+        Self: Sized,
+    {
+        PageCursor::new(
+            page_size,
+            move |page| self.select_retry_candidates(statuses, max_attempts, page),
+            ArxivId::base_id,
+        )
+    }
+
+    /// streams the papers whose keywords were found by `strategy`, a page at a time
+    fn keyword_strategy_pages<'a>(&'a self, page_size: u64, strategy: KeywordStrategy, current_version: Option<&'a str>) -> impl Iterator<Item=AppResult<Vec<ArxivId>>> + 'a
+    where
+        Self: Sized,
+    {
+        PageCursor::new(
+            page_size,
+            move |page| self.select_arxiv_ids_by_keyword_strategy(strategy, current_version, page),
+            ArxivId::base_id,
+        )
+    }
+
+    /// streams training records, a page at a time
+    fn training_data_pages<'a>(&'a self, page_size: u64, search: Option<&'a str>) -> impl Iterator<Item=AppResult<Vec<ArxivPaperContentEntity>>> + 'a
+    where
+        Self: Sized,
+    {
+        PageCursor::new(
+            page_size,
+            move |page| self.select_training_data(page, search),
+            |record| record.id.clone(),
+        )
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use rand::seq::IteratorRandom;
+use crate::content::keyword::KeywordStrategy;
+use crate::content::{ArxivPaperContent, ArxivPaperContentEntity, ExtractionProvenance, PaperText};
+use crate::db::pages::QueryPage;
+use crate::err::{AppError, AppResult};
+use crate::extraction::{ExtractError, ExtractResultRecord};
+use crate::metadata::id::ArxivId;
+use crate::metadata::ArxivMetadata;
+use crate::storage::PaperStore;
+
+struct StoredPaper {
+    abstract_text: String,
+    keywords: Vec<String>,
+    paper_content: String,
+    provenance: Option<ExtractionProvenance>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    /// keyed by the unversioned id, like the `arxiv_metadata` table
+    papers: BTreeMap<String, StoredPaper>,
+    /// every attempt per paper, oldest first
+    extraction_results: BTreeMap<String, Vec<ExtractResultRecord>>,
+    /// keyed by unversioned id, then version (0 when unknown)
+    texts: BTreeMap<String, BTreeMap<u32, PaperText>>,
+}
+
+/// A [PaperStore] that keeps everything in memory and is lost when dropped. It mirrors the SQLite
+/// store's behaviour, except that full-text search isn't supported.
+#[derive(Default)]
+pub struct MemoryPaperStore {
+    state: Mutex<MemoryState>,
+}
+
+impl MemoryPaperStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the stored keywords of a paper, if it is stored
+    pub fn keywords(&self, arxiv_id: &str) -> Option<Vec<String>> {
+        self.lock().papers.get(arxiv_id)
+            .map(|paper| paper.keywords.clone())
+    }
+
+    /// the stored provenance of a paper's extracted content
+    pub fn provenance(&self, arxiv_id: &str) -> Option<ExtractionProvenance> {
+        self.lock().papers.get(arxiv_id)
+            .and_then(|paper| paper.provenance.clone())
+    }
+
+    /// the status code of a paper's latest extraction attempt, such as `OK` or `NO_KEYWORDS`
+    pub fn extraction_status(&self, arxiv_id: &str) -> Option<String> {
+        self.lock().extraction_results.get(arxiv_id)
+            .and_then(|attempts| attempts.last())
+            .map(|record| record.extract_status.clone())
+    }
+
+    /// how many times extraction was attempted on a paper
+    pub fn extraction_attempts(&self, arxiv_id: &str) -> usize {
+        self.lock().extraction_results.get(arxiv_id)
+            .map_or(0, Vec::len)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MemoryState> {
+        self.state.lock()
+            .expect("memory store lock poisoned")
+    }
+}
+
+impl PaperStore for MemoryPaperStore {
+    fn insert_arxiv_metadata(&self, metadata: ArxivMetadata) -> AppResult<()> {
+        let arxiv_id = metadata.arxiv_id()?.base_id();
+        let mut state = self.lock();
+        if state.papers.contains_key(&arxiv_id) {
+            return Err(AppError::Other(format!("paper {} is already stored", arxiv_id)));
+        }
+
+        state.papers.insert(arxiv_id, StoredPaper {
+            abstract_text: metadata.abstract_text().cloned().unwrap_or_default(),
+            keywords: Vec::new(),
+            paper_content: String::new(),
+            provenance: None,
+        });
+        Ok(())
+    }
+
+    fn upsert_content(&self, content: ArxivPaperContent) -> AppResult<()> {
+        let keywords = content.keywords.iter()
+            .map(|keyword| keyword.trim().to_string())
+            .filter(|keyword| !keyword.is_empty())
+            .collect();
+
+        let mut state = self.lock();
+        let paper = state.papers.entry(content.id)
+            .or_insert_with(|| StoredPaper {
+                abstract_text: content.abstract_text,
+                keywords: Vec::new(),
+                paper_content: String::new(),
+                provenance: None,
+            });
+        paper.keywords = keywords;
+        paper.paper_content = content.paper_content;
+        paper.provenance = content.provenance;
+        Ok(())
+    }
+
+    fn insert_extraction_result(&self, id: &str, err: Option<ExtractError>, duration: Duration) -> AppResult<()> {
+        let record = ExtractResultRecord::attempt(id, err, duration);
+        self.lock().extraction_results.entry(record.arxiv_id.clone())
+            .or_default()
+            .push(record);
+        Ok(())
+    }
+
+    fn count_pending_arxiv_ids(&self, max_attempts: Option<u32>) -> AppResult<u64> {
+        let state = self.lock();
+        let count = state.papers.keys()
+            .filter(|arxiv_id| is_pending(&state, arxiv_id, max_attempts))
+            .count();
+        Ok(count as u64)
+    }
+
+    fn select_pending_arxiv_ids(&self, max_attempts: Option<u32>, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        let state = self.lock();
+        let after = (std::ops::Bound::Excluded(page.after.as_str()), std::ops::Bound::Unbounded);
+        state.papers.range::<str, _>(after)
+            .filter(|(arxiv_id, _)| is_pending(&state, arxiv_id, max_attempts))
+            .take(page.limit as usize)
+            .map(|(arxiv_id, _)| ArxivId::parse(arxiv_id))
+            .collect()
+    }
+
+    fn count_retry_candidates(&self, statuses: &[String], max_attempts: Option<u32>) -> AppResult<u64> {
+        let state = self.lock();
+        let count = state.papers.keys()
+            .filter(|arxiv_id| is_retry_candidate(&state, arxiv_id, statuses, max_attempts))
+            .count();
+        Ok(count as u64)
+    }
+
+    fn select_retry_candidates(&self, statuses: &[String], max_attempts: Option<u32>, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        let state = self.lock();
+        let after = (std::ops::Bound::Excluded(page.after.as_str()), std::ops::Bound::Unbounded);
+        state.papers.range::<str, _>(after)
+            .filter(|(arxiv_id, _)| is_retry_candidate(&state, arxiv_id, statuses, max_attempts))
+            .take(page.limit as usize)
+            .map(|(arxiv_id, _)| ArxivId::parse(arxiv_id))
+            .collect()
+    }
+
+    fn insert_paper_text(&self, text: &PaperText) -> AppResult<()> {
+        self.lock().texts.entry(text.arxiv_id.clone())
+            .or_default()
+            .insert(text.version.unwrap_or(0), text.clone());
+        Ok(())
+    }
+
+    fn select_paper_text(&self, arxiv_id: &str) -> AppResult<Option<PaperText>> {
+        let text = self.lock().texts.get(arxiv_id)
+            .and_then(|versions| versions.last_key_value())
+            .map(|(_, text)| text.clone());
+        Ok(text)
+    }
+
+    fn count_papers_with_text(&self) -> AppResult<u64> {
+        Ok(self.lock().texts.len() as u64)
+    }
+
+    fn select_arxiv_ids_with_text(&self, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        let state = self.lock();
+        let after = (std::ops::Bound::Excluded(page.after.as_str()), std::ops::Bound::Unbounded);
+        state.texts.range::<str, _>(after)
+            .take(page.limit as usize)
+            .map(|(arxiv_id, _)| ArxivId::parse(arxiv_id))
+            .collect()
+    }
+
+    fn count_arxiv_ids_by_keyword_strategy(&self, strategy: KeywordStrategy, current_version: Option<&str>) -> AppResult<u64> {
+        let count = self.lock().papers.values()
+            .filter(|paper| is_from_strategy(paper, strategy, current_version))
+            .count();
+        Ok(count as u64)
+    }
+
+    fn select_arxiv_ids_by_keyword_strategy(&self, strategy: KeywordStrategy, current_version: Option<&str>, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        let state = self.lock();
+        let after = (std::ops::Bound::Excluded(page.after.as_str()), std::ops::Bound::Unbounded);
+        state.papers.range::<str, _>(after)
+            .filter(|(_, paper)| is_from_strategy(paper, strategy, current_version))
+            .take(page.limit as usize)
+            .map(|(arxiv_id, _)| ArxivId::parse(arxiv_id))
+            .collect()
+    }
+
+    fn count_arxiv_ids(&self) -> AppResult<u64> {
+        Ok(self.lock().papers.len() as u64)
+    }
+
+    fn select_arxiv_ids(&self, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        let state = self.lock();
+        let after = (std::ops::Bound::Excluded(page.after.as_str()), std::ops::Bound::Unbounded);
+        state.papers.range::<str, _>(after)
+            .take(page.limit as usize)
+            .map(|(arxiv_id, _)| ArxivId::parse(arxiv_id))
+            .collect()
+    }
+
+    fn sample_arxiv_ids(&self, count: u64, unprocessed_only: bool) -> AppResult<Vec<ArxivId>> {
+        let state = self.lock();
+        state.papers.keys()
+            .filter(|arxiv_id| !unprocessed_only || !state.extraction_results.contains_key(*arxiv_id))
+            .choose_multiple(&mut rand::rng(), count as usize)
+            .into_iter()
+            .map(|arxiv_id| ArxivId::parse(arxiv_id))
+            .collect()
+    }
+
+    fn count_training_data(&self, search: Option<&str>) -> AppResult<u64> {
+        reject_search(search)?;
+        let state = self.lock();
+        let count = state.papers.iter()
+            .filter(|(arxiv_id, paper)| is_training_record(&state, arxiv_id, paper))
+            .count();
+        Ok(count as u64)
+    }
+
+    fn select_training_data(&self, page: &QueryPage, search: Option<&str>) -> AppResult<Vec<ArxivPaperContentEntity>> {
+        reject_search(search)?;
+        let state = self.lock();
+        let after = (std::ops::Bound::Excluded(page.after.as_str()), std::ops::Bound::Unbounded);
+        let records = state.papers.range::<str, _>(after)
+            .filter(|(arxiv_id, paper)| is_training_record(&state, arxiv_id, paper))
+            .take(page.limit as usize)
+            .map(|(arxiv_id, paper)| ArxivPaperContentEntity {
+                id: arxiv_id.clone(),
+                abstract_text: paper.abstract_text.clone(),
+                keywords: paper.keywords.clone(),
+                paper_content: paper.paper_content.clone(),
+            })
+            .collect();
+        Ok(records)
+    }
+}
+
+/// same conditions as the `training_data` view
+fn is_training_record(state: &MemoryState, arxiv_id: &str, paper: &StoredPaper) -> bool {
+    let extracted_ok = state.extraction_results.get(arxiv_id)
+        .and_then(|attempts| attempts.last())
+        .is_some_and(|record| record.extract_status == "OK");
+
+    extracted_ok
+        && !paper.paper_content.is_empty()
+        && !paper.abstract_text.is_empty()
+        && !paper.keywords.is_empty()
+}
+
+fn is_pending(state: &MemoryState, arxiv_id: &str, max_attempts: Option<u32>) -> bool {
+    let attempts = state.extraction_results.get(arxiv_id)
+        .map_or(&[][..], Vec::as_slice);
+
+    let extracted_ok = attempts.last()
+        .is_some_and(|record| record.extract_status == "OK");
+    !extracted_ok && max_attempts.is_none_or(|max_attempts| attempts.len() < max_attempts as usize)
+}
+
+fn is_retry_candidate(state: &MemoryState, arxiv_id: &str, statuses: &[String], max_attempts: Option<u32>) -> bool {
+    let Some(attempts) = state.extraction_results.get(arxiv_id) else {
+        return false;
+    };
+
+    let status_matches = attempts.last()
+        .is_some_and(|record| statuses.contains(&record.extract_status));
+    status_matches && max_attempts.is_none_or(|max_attempts| attempts.len() < max_attempts as usize)
+}
+
+fn is_from_strategy(paper: &StoredPaper, strategy: KeywordStrategy, current_version: Option<&str>) -> bool {
+    paper.provenance.as_ref().is_some_and(|provenance| {
+        provenance.keyword_strategy == strategy
+            && current_version.is_none_or(|current_version| provenance.extractor_version != current_version)
+    })
+}
+
+fn reject_search(search: Option<&str>) -> AppResult<()> {
+    match search {
+        Some(_) => Err(AppError::Other("full-text search is not supported by the in-memory store".to_string())),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,100 @@
+use std::time::Duration;
+use crate::content::keyword::KeywordStrategy;
+use crate::content::{ArxivPaperContent, ArxivPaperContentEntity, PaperText};
+use crate::db::pages::QueryPage;
+use crate::db::ArxivDBQueries;
+use crate::err::AppResult;
+use crate::extraction::ExtractError;
+use crate::metadata::id::ArxivId;
+use crate::metadata::ArxivMetadata;
+use crate::storage::PaperStore;
+
+impl PaperStore for ArxivDBQueries<'_> {
+    fn insert_arxiv_metadata(&self, metadata: ArxivMetadata) -> AppResult<()> {
+        ArxivDBQueries::insert_arxiv_metadata(self, metadata)
+    }
+
+    fn upsert_content(&self, content: ArxivPaperContent) -> AppResult<()> {
+        if self.paper_data_exists(&content.id)? {
+            self.update_keywords_and_content(content)
+        } else {
+            let arxiv_id = content.id.clone();
+            self.insert_content(&arxiv_id, content)
+        }
+    }
+
+    fn insert_paper_text(&self, text: &PaperText) -> AppResult<()> {
+        ArxivDBQueries::insert_paper_text(self, text)
+    }
+
+    fn select_paper_text(&self, arxiv_id: &str) -> AppResult<Option<PaperText>> {
+        ArxivDBQueries::select_paper_text(self, arxiv_id)
+    }
+
+    fn count_papers_with_text(&self) -> AppResult<u64> {
+        ArxivDBQueries::count_papers_with_text(self)
+    }
+
+    fn select_arxiv_ids_with_text(&self, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        ArxivDBQueries::select_arxiv_ids_with_text(self, page)
+    }
+
+    fn insert_extraction_result(&self, id: &str, err: Option<ExtractError>, duration: Duration) -> AppResult<()> {
+        ArxivDBQueries::insert_extraction_result(self, id, err, duration)
+    }
+
+    fn count_pending_arxiv_ids(&self, max_attempts: Option<u32>) -> AppResult<u64> {
+        ArxivDBQueries::count_pending_arxiv_ids(self, max_attempts)
+    }
+
+    fn select_pending_arxiv_ids(&self, max_attempts: Option<u32>, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        ArxivDBQueries::select_pending_arxiv_ids(self, max_attempts, page)
+    }
+
+    fn count_retry_candidates(&self, statuses: &[String], max_attempts: Option<u32>) -> AppResult<u64> {
+        ArxivDBQueries::count_retry_candidates(self, statuses, max_attempts)
+    }
+
+    fn select_retry_candidates(&self, statuses: &[String], max_attempts: Option<u32>, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        ArxivDBQueries::select_retry_candidates(self, statuses, max_attempts, page)
+    }
+
+    fn count_arxiv_ids_by_keyword_strategy(&self, strategy: KeywordStrategy, current_version: Option<&str>) -> AppResult<u64> {
+        ArxivDBQueries::count_arxiv_ids_by_keyword_strategy(self, strategy, current_version)
+    }
+
+    fn select_arxiv_ids_by_keyword_strategy(&self, strategy: KeywordStrategy, current_version: Option<&str>, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        ArxivDBQueries::select_arxiv_ids_by_keyword_strategy(self, strategy, current_version, page)
+    }
+
+    fn count_arxiv_ids(&self) -> AppResult<u64> {
+        ArxivDBQueries::count_arxiv_ids(self)
+    }
+
+    fn select_arxiv_ids(&self, page: &QueryPage) -> AppResult<Vec<ArxivId>> {
+        ArxivDBQueries::select_arxiv_ids(self, page)
+    }
+
+    fn sample_arxiv_ids(&self, count: u64, unprocessed_only: bool) -> AppResult<Vec<ArxivId>> {
+        if unprocessed_only {
+            self.sample_arxiv_ids_unprocessed(count)
+        } else {
+            ArxivDBQueries::sample_arxiv_ids(self, count)
+        }
+    }
+
+    fn count_training_data(&self, search: Option<&str>) -> AppResult<u64> {
+        ArxivDBQueries::count_training_data(self, search)
+    }
+
+    fn select_training_data(&self, page: &QueryPage, search: Option<&str>) -> AppResult<Vec<ArxivPaperContentEntity>> {
+        ArxivDBQueries::select_training_data(self, page, search)
+    }
+
+    fn batch<T, BodyT>(&self, body: BodyT) -> AppResult<T>
+    where
+        BodyT: FnOnce(&Self) -> AppResult<T>,
+    {
+        self.transaction(body)
+    }
+}
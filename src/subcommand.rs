@@ -1,19 +1,28 @@
 use clap_derive::Subcommand;
+use crate::subcommand::cache::CacheSubCommands;
 use crate::subcommand::db::DBSubCommands;
 use crate::subcommand::extract::ExtractArgs;
 use crate::subcommand::pull_data::PullDataArgs;
+use crate::subcommand::search::SearchArgs;
 
+pub(crate) mod cache;
 pub(crate) mod db;
 pub(crate) mod extract;
 pub(crate) mod pull_data;
+pub(crate) mod search;
 
 #[derive(Subcommand, Debug)]
 pub enum AppSubCommands {
     /// DB-related features
     #[clap(subcommand)]
     DB(DBSubCommands),
+    /// inspect and maintain a local PDF cache
+    #[clap(subcommand)]
+    Cache(CacheSubCommands),
     /// extract paper metadata
     Extract(ExtractArgs),
     /// pulls training data from the DB
     PullTraining(PullDataArgs),
+    /// full-text search over paper titles, abstracts, bodies and keywords
+    Search(SearchArgs),
 }
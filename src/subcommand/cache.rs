@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+use chrono::DateTime;
+use clap_derive::{Args, Subcommand};
+use keyword_dataset_rs::cache::PdfCache;
+use keyword_dataset_rs::err::{AppError, AppResult};
+use keyword_dataset_rs::metadata::to_iso8601_utc;
+
+#[derive(Subcommand, Debug)]
+pub enum CacheSubCommands {
+    /// show how many PDFs the cache holds and how much space they take
+    Stats(CacheBaseArgs),
+    /// re-hash every cached PDF and report missing or corrupt ones
+    Verify(CacheVerifyArgs),
+    /// evict least recently used PDFs down to a size limit and delete unindexed files
+    Prune(CachePruneArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct CacheBaseArgs {
+    /// path to the PDF cache directory
+    #[arg(short, long)]
+    pub dir: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct CacheVerifyArgs {
+    #[clap(flatten)]
+    pub base: CacheBaseArgs,
+    /// drop missing and corrupt PDFs from the cache so they are downloaded again
+    #[arg(long)]
+    pub repair: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct CachePruneArgs {
+    #[clap(flatten)]
+    pub base: CacheBaseArgs,
+    /// size to shrink the cache to, in MiB
+    #[arg(long)]
+    pub max_mib: u64,
+}
+
+pub fn handle_cache_command(cmd: CacheSubCommands) -> AppResult<()> {
+    match cmd {
+        CacheSubCommands::Stats(args) => cache_stats(args),
+        CacheSubCommands::Verify(args) => verify_cache(args),
+        CacheSubCommands::Prune(args) => prune_cache(args),
+    }
+}
+
+pub fn mib_to_bytes(mib: u64) -> u64 {
+    mib.saturating_mul(1024 * 1024)
+}
+
+fn cache_stats(args: CacheBaseArgs) -> AppResult<()> {
+    let stats = PdfCache::open(&args.dir)?.stats()?;
+    println!("cache {}", args.dir.display());
+    println!("  objects:  {}", stats.objects);
+    println!("  entries:  {}", stats.entries);
+    println!("  size:     {:.1} MiB", stats.total_bytes as f64 / (1024.0 * 1024.0));
+    println!("  oldest use: {}", format_use(stats.oldest_use_ms));
+    println!("  newest use: {}", format_use(stats.newest_use_ms));
+    Ok(())
+}
+
+fn verify_cache(args: CacheVerifyArgs) -> AppResult<()> {
+    let report = PdfCache::open(&args.base.dir)?.verify(args.repair)?;
+    for md5 in &report.missing {
+        println!("missing  {}", md5);
+    }
+    for md5 in &report.corrupt {
+        println!("corrupt  {}", md5);
+    }
+
+    let bad = report.missing.len() + report.corrupt.len();
+    println!("checked {} object(s): {} missing, {} corrupt", report.checked, report.missing.len(), report.corrupt.len());
+    if bad > 0 && !report.repaired {
+        return Err(AppError::Other(format!("{} cached PDF(s) failed verification; re-run with --repair to drop them", bad)));
+    }
+
+    Ok(())
+}
+
+fn prune_cache(args: CachePruneArgs) -> AppResult<()> {
+    let report = PdfCache::open(&args.base.dir)?.prune(mib_to_bytes(args.max_mib))?;
+    println!(
+        "evicted {} object(s) ({:.1} MiB), removed {} orphaned file(s)",
+        report.evicted_objects,
+        report.evicted_bytes as f64 / (1024.0 * 1024.0),
+        report.removed_orphans
+    );
+    Ok(())
+}
+
+fn format_use(timestamp_ms: Option<i64>) -> String {
+    timestamp_ms
+        .and_then(DateTime::from_timestamp_millis)
+        .map(|timestamp| to_iso8601_utc(&timestamp))
+        .unwrap_or_else(|| "-".to_string())
+}
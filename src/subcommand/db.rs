@@ -1,14 +1,72 @@
+mod check;
+mod harvest;
 mod load;
+mod migrate;
+mod stats;
 
 use std::path::PathBuf;
-use clap_derive::{Args, Subcommand};
+use std::time::Duration;
+use clap_derive::{Args, Subcommand, ValueEnum};
+use keyword_dataset_rs::db::{ArxivDB, ConnectionOptions, Durability, JournalMode};
 use keyword_dataset_rs::err::AppResult;
+use crate::subcommand::db::check::{check_db, DBCheckArgs};
+use crate::subcommand::db::harvest::{harvest_db, DBHarvestArgs};
 use crate::subcommand::db::load::{load_db, DBLoadArgs};
+use crate::subcommand::db::migrate::{migrate_db, DBMigrateArgs};
+use crate::subcommand::db::stats::{db_stats, DBStatsArgs};
 
 #[derive(Subcommand, Debug)]
 pub enum DBSubCommands {
     /// load data into a new database
     Load(DBLoadArgs),
+    /// harvest metadata from an OAI-PMH endpoint into a database
+    Harvest(DBHarvestArgs),
+    /// apply pending schema migrations
+    Migrate(DBMigrateArgs),
+    /// report paper, extraction and training data counts
+    Stats(DBStatsArgs),
+    /// look for duplicate, orphaned and inconsistent rows, and optionally repair them
+    Check(DBCheckArgs),
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum JournalModeArg {
+    /// write-ahead log, lets other processes read while this one writes
+    Wal,
+    /// classic rollback journal
+    Delete,
+    /// rollback journal, truncated instead of deleted
+    Truncate,
+}
+
+impl From<JournalModeArg> for JournalMode {
+    fn from(value: JournalModeArg) -> Self {
+        match value {
+            JournalModeArg::Wal => JournalMode::Wal,
+            JournalModeArg::Delete => JournalMode::Delete,
+            JournalModeArg::Truncate => JournalMode::Truncate,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum DurabilityArg {
+    /// fastest; a crash or power loss can corrupt the db
+    Off,
+    /// safe against corruption in WAL mode, may lose the last commits on power loss
+    Normal,
+    /// sync on every commit
+    Full,
+}
+
+impl From<DurabilityArg> for Durability {
+    fn from(value: DurabilityArg) -> Self {
+        match value {
+            DurabilityArg::Off => Durability::Off,
+            DurabilityArg::Normal => Durability::Normal,
+            DurabilityArg::Full => Durability::Full,
+        }
+    }
 }
 
 #[derive(Args, Debug)]
@@ -16,10 +74,33 @@ pub struct DBBaseArgs {
     /// path to the SQLite metadata db that we want to operate on
     #[arg(short, long)]
     pub db: PathBuf,
+    /// SQLite journal mode
+    #[arg(long, value_enum, default_value = "wal")]
+    pub journal_mode: JournalModeArg,
+    /// how much to trade crash safety for write speed
+    #[arg(long, value_enum, default_value = "normal")]
+    pub durability: DurabilityArg,
+    /// milliseconds to wait for a lock held by another process before failing
+    #[arg(long, default_value_t = 5000)]
+    pub busy_timeout_ms: u64,
+}
+
+impl DBBaseArgs {
+    pub fn open_db(&self) -> AppResult<ArxivDB> {
+        let options = ConnectionOptions::new()
+            .with_journal_mode(self.journal_mode.into())
+            .with_durability(self.durability.into())
+            .with_busy_timeout(Duration::from_millis(self.busy_timeout_ms));
+        ArxivDB::open_with_options(&self.db, &options)
+    }
 }
 
 pub fn handle_db_command(cmd: DBSubCommands) -> AppResult<()> {
     match cmd {
         DBSubCommands::Load(args) => load_db(args),
+        DBSubCommands::Harvest(args) => harvest_db(args),
+        DBSubCommands::Migrate(args) => migrate_db(args),
+        DBSubCommands::Stats(args) => db_stats(args),
+        DBSubCommands::Check(args) => check_db(args),
     }
 }
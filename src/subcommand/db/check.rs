@@ -0,0 +1,65 @@
+use clap_derive::Args;
+use keyword_dataset_rs::db::check::{CHECK_SCHEMA_VERSION, INTEGRITY_CHECKS};
+use keyword_dataset_rs::err::{AppError, AppResult};
+use crate::subcommand::db::DBBaseArgs;
+
+#[derive(Args, Debug)]
+pub struct DBCheckArgs {
+    #[clap(flatten)]
+    pub base: DBBaseArgs,
+    /// fix what the checks find, in a single transaction, then apply the migrations that needed
+    /// the fixes
+    #[arg(long)]
+    pub repair: bool,
+}
+
+pub fn check_db(args: DBCheckArgs) -> AppResult<()> {
+    let mut db = args.base.open_db()?;
+    // a plain check only reads, so leave the schema alone. A repair stops short of the migrations
+    // that would otherwise trip over what it repairs.
+    if args.repair {
+        db.migrate_to(CHECK_SCHEMA_VERSION)?;
+    } else {
+        db.require_schema(CHECK_SCHEMA_VERSION)?;
+    }
+
+    let findings = if args.repair {
+        db.queries().repair_integrity()?
+    } else {
+        db.queries().check_integrity()?
+    };
+
+    for (check, finding) in INTEGRITY_CHECKS.iter().zip(&findings) {
+        let state = match (finding.found, finding.repaired) {
+            (0, _) => "ok",
+            (_, Some(_)) => "fixed",
+            (_, None) => "FAIL",
+        };
+        println!("  {:<6} {:<22} {:>8}  {}", state, finding.name, finding.found, finding.description);
+        if let Some(repaired) = finding.repaired.filter(|repaired| *repaired > 0) {
+            println!("         {:<22} {:>8}  {}", "", repaired, check.repair);
+        } else if finding.found > 0 && !check.is_repairable() {
+            println!("         {:<22} {:>8}  {}", "", "", check.repair);
+        }
+    }
+
+    if args.repair {
+        for migration in db.migrate()? {
+            println!("applied {}", migration);
+        }
+    }
+
+    let problems = findings.iter().filter(|finding| finding.found > 0).count();
+    let unrepaired = findings.iter().filter(|finding| finding.found > 0 && finding.repaired.is_none()).count();
+    if problems == 0 {
+        println!("no problems found");
+    } else if !args.repair {
+        return Err(AppError::Other(format!("{} check(s) failed; re-run with --repair to fix what can be fixed", problems)));
+    } else if unrepaired > 0 {
+        return Err(AppError::Other(format!("repaired {} problem(s), {} need fixing by hand", problems - unrepaired, unrepaired)));
+    } else {
+        println!("repaired {} problem(s)", problems);
+    }
+
+    Ok(())
+}
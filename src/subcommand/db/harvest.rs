@@ -0,0 +1,115 @@
+use std::time::Duration;
+use chrono::NaiveDate;
+use clap_derive::{Args, ValueEnum};
+use log::{debug, error, info};
+use keyword_dataset_rs::db::{ArxivDBQueries, UpsertOutcome};
+use keyword_dataset_rs::err::AppResult;
+use keyword_dataset_rs::harvest::{HarvestRequest, MetadataFormat, OaiHarvester, DEFAULT_OAI_ENDPOINT};
+use crate::subcommand::db::DBBaseArgs;
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum MetadataFormatArg {
+    /// `arXiv` format: structured authors, first version date only
+    Arxiv,
+    /// `arXivRaw` format: every version with its date
+    ArxivRaw,
+}
+
+impl From<MetadataFormatArg> for MetadataFormat {
+    fn from(value: MetadataFormatArg) -> Self {
+        match value {
+            MetadataFormatArg::Arxiv => MetadataFormat::Arxiv,
+            MetadataFormatArg::ArxivRaw => MetadataFormat::ArxivRaw,
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct DBHarvestArgs {
+    #[clap(flatten)]
+    pub base: DBBaseArgs,
+    /// OAI-PMH endpoint to harvest from
+    #[arg(long, default_value = DEFAULT_OAI_ENDPOINT)]
+    pub endpoint: String,
+    /// metadata format to request
+    #[arg(long, value_enum, default_value = "arxiv-raw")]
+    pub format: MetadataFormatArg,
+    /// OAI set to harvest, such as `cs` or `math`
+    #[arg(long)]
+    pub set: Option<String>,
+    /// only harvest records updated on or after this date (YYYY-MM-DD)
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+    /// only harvest records updated on or before this date (YYYY-MM-DD)
+    #[arg(long)]
+    pub until: Option<NaiveDate>,
+    /// continue an interrupted harvest from the resumption token it logged. Use the same format
+    /// as the original run
+    #[arg(long, conflicts_with_all = ["set", "from", "until"])]
+    pub resumption_token: Option<String>,
+    /// seconds to wait between requests, to respect the endpoint's rate limits
+    #[arg(long, default_value_t = 3)]
+    pub delay_secs: u64,
+}
+
+pub fn harvest_db(args: DBHarvestArgs) -> AppResult<()> {
+    let harvester = OaiHarvester::new(&args.endpoint)
+        .with_request_delay(Duration::from_secs(args.delay_secs));
+    let request = HarvestRequest {
+        format: args.format.into(),
+        set: args.set.clone(),
+        from: args.from,
+        until: args.until,
+        resumption_token: args.resumption_token.clone(),
+    };
+
+    let mut db = args.base.open_db()?;
+    db.migrate()?;
+
+    info!("harvesting {} records from {}", request.format, args.endpoint);
+    let mut pages = harvester.harvest(request);
+    let mut resume_token = args.resumption_token.clone();
+    let mut committed_pages = 0u64;
+    let mut inserted_records = 0u64;
+    let mut updated_records = 0u64;
+    let mut unchanged_records = 0u64;
+    while let Some(page) = pages.next_page() {
+        // each page is committed on its own, so a failure only loses the page in flight
+        let page = page.and_then(|page| {
+            let txn = db.txn()?;
+            let queries = ArxivDBQueries::wrap(&txn);
+            for metadata in page.records {
+                match queries.upsert_arxiv_metadata(metadata)? {
+                    UpsertOutcome::Inserted => inserted_records += 1,
+                    UpsertOutcome::Updated => updated_records += 1,
+                    UpsertOutcome::Unchanged => unchanged_records += 1,
+                }
+            }
+            txn.commit()?;
+            Ok(page.resumption_token)
+        });
+
+        match page {
+            Ok(next_token) => {
+                committed_pages += 1;
+                resume_token = next_token;
+                if let Some(token) = &resume_token {
+                    debug!("committed page {}, next resumption token {}", committed_pages, token);
+                }
+            }
+            Err(err) => {
+                match &resume_token {
+                    Some(token) => error!("harvest stopped after {} committed page(s); continue with --resumption-token '{}'", committed_pages, token),
+                    None => error!("harvest stopped before its first page was committed"),
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    println!(
+        "finished harvesting {} page(s): {} new, {} updated, {} unchanged item(s), {} deleted record(s) skipped",
+        committed_pages, inserted_records, updated_records, unchanged_records, pages.deleted_records()
+    );
+    Ok(())
+}
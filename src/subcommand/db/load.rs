@@ -1,66 +1,247 @@
 use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::path::PathBuf;
-use chrono::{DateTime, FixedOffset, TimeZone, Utc};
-use clap_derive::Args;
-use regex::Regex;
-use keyword_dataset_rs::db::{ArxivDB, ArxivDBQueries};
-use keyword_dataset_rs::err::AppResult;
-use keyword_dataset_rs::metadata::reader::ArxivMetadataIter;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, FixedOffset};
+use indicatif::{ProgressBar, ProgressStyle};
+use clap_derive::{Args, ValueEnum};
+use keyword_dataset_rs::db::{ArxivDBQueries, UpsertOutcome};
+use keyword_dataset_rs::err::{AppError, AppResult};
+use log::{error, info, warn};
+use keyword_dataset_rs::metadata::input::open_metadata_input;
+use keyword_dataset_rs::metadata::filter::{parse_date_bound, CategoryPattern, DateRange, MetadataFilter, VersionSelector};
+use keyword_dataset_rs::metadata::parallel::ParallelMetadataReader;
 use crate::subcommand::db::DBBaseArgs;
 
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum DateVersionArg {
+    /// filter on the date of the first version
+    First,
+    /// filter on the date of the latest version
+    Last,
+}
+
+impl From<DateVersionArg> for VersionSelector {
+    fn from(value: DateVersionArg) -> Self {
+        match value {
+            DateVersionArg::First => VersionSelector::First,
+            DateVersionArg::Last => VersionSelector::Last,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnErrorArg {
+    /// skip malformed lines and keep loading
+    Skip,
+    /// stop loading at the first malformed line
+    Abort,
+}
+
 #[derive(Args, Debug)]
 pub struct DBLoadArgs {
     #[clap(flatten)]
     pub base: DBBaseArgs,
-    /// path to the arxiv metadata file dump from kaggle
+    /// path to the arxiv metadata file dump from kaggle, or `-` for STDIN. gzip, zstd, bzip2 and
+    /// xz compressed dumps are detected and decompressed automatically
     pub metadata_file: PathBuf,
+    /// only load papers created on or after this date (YYYY-MM-DD or RFC 3339)
+    #[arg(long, value_parser = parse_date_arg)]
+    pub since: Option<DateTime<FixedOffset>>,
+    /// only load papers created before this date (YYYY-MM-DD or RFC 3339)
+    #[arg(long, value_parser = parse_date_arg)]
+    pub until: Option<DateTime<FixedOffset>>,
+    /// which version's creation date the date range applies to
+    #[arg(long, value_enum, default_value = "first")]
+    pub date_version: DateVersionArg,
+    /// category globs to include, such as `cs.*` or `stat.ML`. May be repeated or comma-separated
+    #[arg(long, value_delimiter = ',', default_value = "cs.*")]
+    pub include: Vec<String>,
+    /// category globs to exclude. May be repeated or comma-separated
+    #[arg(long, value_delimiter = ',')]
+    pub exclude: Vec<String>,
+    /// only match categories against the primary (first listed) category
+    #[arg(long, default_value_t = false)]
+    pub primary_only: bool,
+    /// what to do when a line of the dump cannot be parsed
+    #[arg(long, value_enum, default_value = "abort")]
+    pub on_error: OnErrorArg,
+    /// file to write skipped lines to, verbatim, so they can be fixed and reloaded
+    #[arg(long)]
+    pub rejects: Option<PathBuf>,
+    /// update an existing database from a newer snapshot: changed metadata is updated, new
+    /// versions are appended and extracted content is kept
+    #[arg(long, default_value_t = false)]
+    pub incremental: bool,
+    /// how many threads parse JSON. 0 will use available parallelism
+    #[arg(short = 'j', long, default_value_t = 0usize)]
+    pub parallelism: usize,
+    /// how many lines are parsed and inserted together
+    #[arg(long, default_value_t = 1000usize)]
+    pub batch_size: usize,
+}
+
+impl DBLoadArgs {
+    pub fn parallelism(&self) -> usize {
+        if self.parallelism == 0 {
+            return std::thread::available_parallelism().unwrap().get();
+        }
+
+        self.parallelism
+    }
+
+    pub fn metadata_filter(&self) -> AppResult<MetadataFilter> {
+        let include = self.include.iter()
+            .map(CategoryPattern::new)
+            .collect::<AppResult<Vec<_>>>()?;
+        let exclude = self.exclude.iter()
+            .map(CategoryPattern::new)
+            .collect::<AppResult<Vec<_>>>()?;
+
+        let filter = MetadataFilter::new()
+            .with_date_range(DateRange::new(self.since, self.until), self.date_version.into())
+            .with_include(include)
+            .with_exclude(exclude)
+            .with_primary_only(self.primary_only);
+
+        Ok(filter)
+    }
+}
+
+fn parse_date_arg(value: &str) -> Result<DateTime<FixedOffset>, String> {
+    parse_date_bound(value).map_err(|err| err.to_string())
+}
+
+#[derive(Default)]
+struct LoadStats {
+    lines: u64,
+    inserted: u64,
+    updated: u64,
+    unchanged: u64,
+    filtered_out: u64,
+    skipped: u64,
 }
 
 pub fn load_db(args: DBLoadArgs) -> AppResult<()> {
-    
-    let file = File::open(&args.metadata_file)?;
-    let mut db = ArxivDB::open(&args.base.db)?;
-    
-    // execute the DDL
-    db.execute_ddl()?;
+
+    let metadata_filter = args.metadata_filter()?;
+    let input = open_metadata_input(&args.metadata_file)?;
+    let mut rejects = args.rejects.as_ref()
+        .map(|path| File::create(path).map(BufWriter::new))
+        .transpose()?;
+    let mut db = args.base.open_db()?;
+
+    // bring the schema up to date
+    db.migrate()?;
 
     let txn = db.txn()?;
     let queries = ArxivDBQueries::wrap(&txn);
 
-    let start_date = Utc.with_ymd_and_hms(2020, 01, 01, 06, 00, 00).unwrap();
-    let start_date = DateTime::<FixedOffset>::from(start_date);
-
-    // perform our filtration query and insert records into database
-    let cs_category_filter = CsCategoryFilter::new();
-    let metadata_iter = ArxivMetadataIter::new(file);
-    let mut inserted_records = 0u64;
-    metadata_iter.into_iter()
-        .filter(|metadata| metadata.versions().first().is_some_and(|version| version.is_after(&start_date)))
-        .filter(|metadata| metadata.categories().is_some_and(|categories| cs_category_filter.has_cs_categories(categories)))
-        .for_each(|metadata| {
-            queries.insert_arxiv_metadata(metadata).expect("failed to insert item into db");
-            inserted_records += 1;
-        });
+    let bytes_read = input.bytes_read();
+    let progress = create_progress_bar(input.total_bytes());
+    let start = Instant::now();
+
+    // parse and filter on worker threads, insert here on a single writer
+    let parser = ParallelMetadataReader::new(args.parallelism())
+        .with_chunk_lines(args.batch_size);
+    let mut stats = LoadStats::default();
+    parser.for_each_chunk(input, &metadata_filter, |chunk| {
+        stats.lines += chunk.lines;
+        stats.filtered_out += chunk.filtered_out;
+
+        let mut batch = Vec::with_capacity(chunk.records.len());
+        for record in chunk.records {
+            match record {
+                Ok(metadata) => batch.push(metadata),
+                Err(AppError::InvalidMetadataRecord { line, offset, raw, source }) if args.on_error == OnErrorArg::Skip => {
+                    progress.suspend(|| warn!("skipping malformed record on line {} (byte offset {}): {}", line, offset, source));
+                    if let Some(rejects) = rejects.as_mut() {
+                        writeln!(rejects, "{}", raw)?;
+                    }
+                    stats.skipped += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        if args.incremental {
+            for metadata in batch {
+                match queries.upsert_arxiv_metadata(metadata)? {
+                    UpsertOutcome::Inserted => stats.inserted += 1,
+                    UpsertOutcome::Updated => stats.updated += 1,
+                    UpsertOutcome::Unchanged => stats.unchanged += 1,
+                }
+            }
+        } else {
+            if let Err(err) = queries.insert_arxiv_metadata_batch(&batch) {
+                progress.suspend(|| error!("failed to insert records, use --incremental to reload into an existing db"));
+                return Err(err);
+            }
+            stats.inserted += batch.len() as u64;
+        }
+
+        progress.set_position(bytes_read.load(Ordering::Relaxed));
+        progress.set_message(format!("{} record(s)", stats.inserted + stats.updated + stats.unchanged));
+        Ok(())
+    }).inspect_err(|err| {
+        progress.abandon();
+        error!("aborting load and rolling back {} inserted record(s): {}", stats.inserted, err);
+    })?;
+
+    progress.set_position(bytes_read.load(Ordering::Relaxed));
+    progress.finish();
 
     txn.commit()?;
 
-    println!("finished writing {} item(s) to db", inserted_records);
-    Ok(())
-}
+    if let Some(mut rejects) = rejects {
+        rejects.flush()?;
+    }
+    if stats.skipped > 0 {
+        if let Some(path) = args.rejects.as_ref() {
+            info!("wrote {} rejected line(s) to {}", stats.skipped, path.display());
+        }
+    }
 
-struct CsCategoryFilter {
-    filter: Regex,
+    print_summary(&stats, bytes_read.load(Ordering::Relaxed), start.elapsed(), args.incremental);
+    Ok(())
 }
 
-impl CsCategoryFilter {
-    pub fn new() -> Self {
-        Self {
-            filter: Regex::new(r"^cs.\w\w$").unwrap()
+fn create_progress_bar(total_bytes: Option<u64>) -> ProgressBar {
+    match total_bytes {
+        Some(total_bytes) => {
+            let style = ProgressStyle::with_template("{bar:40} {bytes}/{total_bytes} ({bytes_per_sec}, eta {eta}) {msg}")
+                .expect("progress template should be valid");
+            ProgressBar::new(total_bytes).with_style(style)
+        }
+        None => {
+            let style = ProgressStyle::with_template("{spinner} {bytes} read ({bytes_per_sec}) {msg}")
+                .expect("progress template should be valid");
+            let progress = ProgressBar::new_spinner().with_style(style);
+            progress.enable_steady_tick(Duration::from_millis(200));
+            progress
         }
     }
-    
-    pub fn has_cs_categories(&self, categories: &str) -> bool {
-        categories.split(" ")
-            .any(|single| self.filter.is_match(single))
+}
+
+fn print_summary(stats: &LoadStats, bytes_read: u64, elapsed: Duration, incremental: bool) {
+    if incremental {
+        println!(
+            "finished loading: {} new, {} updated, {} unchanged item(s), skipped {} malformed line(s)",
+            stats.inserted, stats.updated, stats.unchanged, stats.skipped
+        );
+    } else {
+        println!("finished writing {} item(s) to db, skipped {} malformed line(s)", stats.inserted, stats.skipped);
     }
+
+    let seconds = elapsed.as_secs_f64().max(f64::EPSILON);
+    println!(
+        "read {} line(s) ({:.1} MiB, {} filtered out) in {:.1}s: {:.0} lines/s, {:.1} MiB/s",
+        stats.lines,
+        bytes_read as f64 / (1024.0 * 1024.0),
+        stats.filtered_out,
+        seconds,
+        stats.lines as f64 / seconds,
+        bytes_read as f64 / (1024.0 * 1024.0) / seconds,
+    );
 }
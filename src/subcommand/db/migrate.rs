@@ -0,0 +1,74 @@
+use clap_derive::Args;
+use keyword_dataset_rs::db::migration::{SchemaStatus, LATEST_SCHEMA_VERSION, MIGRATIONS};
+use keyword_dataset_rs::err::AppResult;
+use crate::subcommand::db::DBBaseArgs;
+
+#[derive(Args, Debug)]
+pub struct DBMigrateArgs {
+    #[clap(flatten)]
+    pub base: DBBaseArgs,
+    /// only show which migrations have been applied and which are pending
+    #[arg(long, conflicts_with = "dry_run")]
+    pub status: bool,
+    /// print the SQL of the pending migrations without applying them
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+pub fn migrate_db(args: DBMigrateArgs) -> AppResult<()> {
+    let mut db = args.base.open_db()?;
+    let status = db.schema_status()?;
+
+    if args.status {
+        print_status(&status);
+        return Ok(());
+    }
+
+    if args.dry_run {
+        print_dry_run(&status);
+        return Ok(());
+    }
+
+    let applied = db.migrate()?;
+    if applied.is_empty() {
+        println!("schema is up to date at version {}", LATEST_SCHEMA_VERSION);
+    } else {
+        for migration in &applied {
+            println!("applied {}", migration);
+        }
+        println!("migrated schema from version {} to {}", status.version(), LATEST_SCHEMA_VERSION);
+    }
+
+    Ok(())
+}
+
+fn print_status(status: &SchemaStatus) {
+    println!("schema version {} of {}", status.version(), LATEST_SCHEMA_VERSION);
+    if let Some(legacy_version) = status.legacy_version {
+        println!("unversioned database, schema matches version {}", legacy_version);
+    }
+
+    for migration in MIGRATIONS {
+        let state = if migration.version <= status.version() { "applied" } else { "pending" };
+        println!("  {:<8} {}", state, migration);
+    }
+}
+
+fn print_dry_run(status: &SchemaStatus) {
+    if let Some(legacy_version) = status.legacy_version {
+        println!("-- would stamp unversioned database as version {}", legacy_version);
+    }
+
+    let mut pending = status.pending().peekable();
+    if pending.peek().is_none() {
+        println!("-- schema is up to date at version {}", LATEST_SCHEMA_VERSION);
+        return;
+    }
+
+    for migration in pending {
+        println!("-- {}", migration);
+        println!("{}", migration.sql.trim_end());
+        println!("PRAGMA user_version = {};", migration.version);
+        println!();
+    }
+}
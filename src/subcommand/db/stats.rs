@@ -0,0 +1,90 @@
+use std::io::{BufWriter, Write};
+use clap_derive::{Args, ValueEnum};
+use keyword_dataset_rs::db::stats::{DatasetStats, StatsBreakdown};
+use keyword_dataset_rs::err::AppResult;
+use crate::subcommand::db::DBBaseArgs;
+
+#[derive(ValueEnum, Debug, Clone)]
+pub enum StatsOutputFormat {
+    /// human-readable report
+    Text,
+    /// a single JSON object
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub struct DBStatsArgs {
+    #[clap(flatten)]
+    pub base: DBBaseArgs,
+    /// format of the report
+    #[arg(short, long, value_enum, default_value = "text")]
+    pub format: StatsOutputFormat,
+}
+
+pub fn db_stats(args: DBStatsArgs) -> AppResult<()> {
+    let db = args.base.open_db()?;
+    db.require_current_schema()?;
+
+    let stats = db.queries().dataset_stats()?;
+
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    match args.format {
+        StatsOutputFormat::Text => write_text(&mut out, &stats)?,
+        StatsOutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, &stats)?;
+            writeln!(out)?;
+        }
+    }
+
+    out.flush()?;
+    Ok(())
+}
+
+fn write_text<WriterT: Write>(out: &mut WriterT, stats: &DatasetStats) -> AppResult<()> {
+    writeln!(out, "papers:         {}", stats.papers)?;
+    writeln!(out, "  processed:    {} ({})", stats.processed, percent(stats.processed, stats.papers))?;
+    writeln!(out, "  unprocessed:  {}", stats.unprocessed)?;
+    writeln!(out, "training rows:  {} ({})", stats.training_rows, percent(stats.training_rows, stats.papers))?;
+    writeln!(out, "avg keywords:   {}", format_average(stats.average_keywords))?;
+    writeln!(out, "avg body chars: {}", format_average(stats.average_body_chars))?;
+
+    writeln!(out)?;
+    writeln!(out, "extraction status:")?;
+    for status in &stats.statuses {
+        writeln!(out, "  {:<20} {:>10} ({})", status.status_code, status.count, percent(status.count, stats.processed))?;
+    }
+
+    writeln!(out)?;
+    writeln!(out, "keywords per paper with an extracted body:")?;
+    for bucket in &stats.keyword_counts {
+        writeln!(out, "  {:>3} {:>10}", bucket.keywords, bucket.papers)?;
+    }
+
+    write_breakdown(out, "primary category", &stats.by_primary_category)?;
+    write_breakdown(out, "year", &stats.by_year)?;
+    Ok(())
+}
+
+fn write_breakdown<WriterT: Write>(out: &mut WriterT, title: &str, rows: &[StatsBreakdown]) -> AppResult<()> {
+    writeln!(out)?;
+    writeln!(out, "by {}:", title)?;
+    writeln!(out, "  {:<20} {:>10} {:>10} {:>10}", "", "papers", "processed", "training")?;
+    for row in rows {
+        writeln!(out, "  {:<20} {:>10} {:>10} {:>10}", row.key, row.papers, row.processed, row.training_rows)?;
+    }
+    Ok(())
+}
+
+fn percent(part: u64, total: u64) -> String {
+    if total == 0 {
+        return "-".to_string();
+    }
+
+    format!("{:.1}%", part as f64 * 100.0 / total as f64)
+}
+
+fn format_average(average: Option<f64>) -> String {
+    average
+        .map(|average| format!("{:.1}", average))
+        .unwrap_or_else(|| "-".to_string())
+}
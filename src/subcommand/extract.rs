@@ -1,14 +1,32 @@
+use crate::subcommand::cache::mib_to_bytes;
 use crate::subcommand::db::DBBaseArgs;
-use clap_derive::Args;
-use keyword_dataset_rs::content::ArxivPaperContent;
-use keyword_dataset_rs::db::pages::page_iter;
-use keyword_dataset_rs::db::{ArxivDB, ArxivDBQueries};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap_derive::{Args, ValueEnum};
+use keyword_dataset_rs::cache::PdfCache;
 use keyword_dataset_rs::err::AppResult;
-use keyword_dataset_rs::extraction::{ContentExtractor, ExtractResult};
-use log::{debug, error, info};
-use std::ops::Deref;
+use keyword_dataset_rs::content::keyword::KeywordStrategy;
+use keyword_dataset_rs::extraction::{ContentExtractor, EXTRACTOR_VERSION};
+use keyword_dataset_rs::extraction::pipeline::ExtractionPipeline;
+use keyword_dataset_rs::fetch::local::{FlatDirSource, LocalTreeSource};
+use keyword_dataset_rs::fetch::rate::{RateLimiter, DEFAULT_DOWNLOAD_RATE, DEFAULT_LIST_RATE, MIN_RATE};
+use keyword_dataset_rs::fetch::source::{GcsSource, PaperSource};
+use keyword_dataset_rs::storage::PaperStore;
+use log::{error, info};
+use std::path::PathBuf;
 use std::sync::Arc;
-use threadpool::ThreadPool;
+
+/// ids fetched and extracted per round when processing every paper
+const ID_PAGE_SIZE: u64 = 100;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaperSourceArg {
+    /// the public `arxiv-dataset` bucket on Google Cloud Storage
+    Gcs,
+    /// a local copy of the bucket's `arxiv/` prefix, with `<archive>/pdf/YYMM/` directories
+    Tree,
+    /// a single directory of `<id>vN.pdf` files
+    Flat,
+}
 
 #[derive(Args, Debug)]
 pub struct ExtractArgs {
@@ -21,6 +39,53 @@ pub struct ExtractArgs {
     /// extraction_result
     #[arg(short, long, default_value_t = false)]
     unique: bool,
+    /// re-run only the keyword and body stages over the paper text stored by earlier runs, without
+    /// downloading anything
+    #[arg(long, conflicts_with_all = ["count", "unique"])]
+    from_cache: bool,
+    /// process every paper whose latest extraction isn't OK, including papers never attempted.
+    /// Runs can be interrupted and started again, and pick up where they left off
+    #[arg(long, group = "attempt_mode", conflicts_with_all = ["count", "unique", "from_cache"])]
+    pending: bool,
+    /// retry papers whose latest extraction failed with one of these status codes, such as
+    /// `NETWORK,HTTP_STAT`
+    #[arg(long, value_delimiter = ',', group = "attempt_mode", conflicts_with_all = ["count", "unique", "from_cache"])]
+    retry_status: Vec<String>,
+    /// re-extract papers whose keywords were found by this strategy
+    #[arg(long, value_parser = keyword_strategy_parser(), conflicts_with_all = ["count", "unique", "from_cache", "attempt_mode"])]
+    strategy: Option<KeywordStrategy>,
+    /// used with strategy. Only re-extract papers extracted by another version of the extractor
+    /// than this one
+    #[arg(long, requires = "strategy")]
+    outdated: bool,
+    /// used with pending or retry-status. Skip papers that have already been attempted this many
+    /// times
+    #[arg(long, requires = "attempt_mode")]
+    max_attempts: Option<u32>,
+    /// where to read paper PDFs from
+    #[arg(long, value_enum, default_value = "gcs")]
+    source: PaperSourceArg,
+    /// root directory of a tree or flat source
+    #[arg(long, required_if_eq_any = [("source", "tree"), ("source", "flat")])]
+    source_dir: Option<PathBuf>,
+    /// object listings per second allowed against the GCS bucket, shared by all threads. 0 removes
+    /// the limit
+    #[arg(long, default_value_t = DEFAULT_LIST_RATE, value_parser = parse_rate)]
+    list_rate: f64,
+    /// PDF downloads per second allowed from the GCS bucket, shared by all threads. 0 removes the
+    /// limit
+    #[arg(long, default_value_t = DEFAULT_DOWNLOAD_RATE, value_parser = parse_rate)]
+    download_rate: f64,
+    /// requests each GCS budget allows back to back after being idle
+    #[arg(long, default_value_t = 1)]
+    rate_burst: u32,
+    /// directory of downloaded PDFs to reuse and add to. Can be shared between machines on a
+    /// network volume with working file locks, such as NFSv4 or SMB
+    #[arg(long)]
+    pdf_cache: Option<PathBuf>,
+    /// evict least recently used PDFs once the cache grows past this many MiB
+    #[arg(long, requires = "pdf_cache")]
+    pdf_cache_max_mib: Option<u64>,
     /// how many threads are available. 0 will use available parallelism
     #[arg(short = 'j', long, default_value_t = 0usize)]
     parallelism: usize,
@@ -34,10 +99,26 @@ impl ExtractArgs {
 
         self.parallelism
     }
+
+    /// the GCS listing and download budgets, or `None` where unlimited
+    pub fn rate_limiters(&self) -> (Option<Arc<RateLimiter>>, Option<Arc<RateLimiter>>) {
+        let limiter = |name, rate: f64| (rate > 0.0)
+            .then(|| Arc::new(RateLimiter::new(name, rate, self.rate_burst)));
+        (limiter("list", self.list_rate), limiter("download", self.download_rate))
+    }
+
+    pub fn paper_source(&self, list_limiter: Option<Arc<RateLimiter>>, download_limiter: Option<Arc<RateLimiter>>) -> Box<dyn PaperSource> {
+        let source_dir = || self.source_dir.clone().expect("clap requires a directory for local sources");
+        match self.source {
+            PaperSourceArg::Gcs => Box::new(GcsSource::unthrottled().with_rate_limits(list_limiter, download_limiter)),
+            PaperSourceArg::Tree => Box::new(LocalTreeSource::new(source_dir())),
+            PaperSourceArg::Flat => Box::new(FlatDirSource::new(source_dir())),
+        }
+    }
 }
 
 pub fn extract_and_save_contents(args: ExtractArgs) -> AppResult<()> {
-    let mut db = match ArxivDB::open(&args.db.db) {
+    let mut db = match args.db.open_db() {
         Ok(db) => {
             info!("successfully connected to db {}", args.db.db.display());
             db
@@ -48,160 +129,97 @@ pub fn extract_and_save_contents(args: ExtractArgs) -> AppResult<()> {
         }
     };
 
-    // execute the DDL and turn off synchronous
-    db.execute_ddl()?;
-    db.turn_off_synchronous()?;
-
-    let txn = db.txn()?;
-    let queries = ArxivDBQueries::wrap(txn.deref());
+    // bring the schema up to date
+    db.migrate()?;
 
-    let (total_ids, is_sample) = if let Some(sample_size) = args.count {
-        info!("going to process random sample of size {}", sample_size);
-        (sample_size, true)
-    } else {
-        let total_ids = queries.count_arxiv_ids()?;
-        info!("going to process all {} id(s)", total_ids);
-        (total_ids, false)
-    };
+    // results are committed batch by batch, so other processes can read them while we run
+    let store = db.queries();
 
     let pool = threadpool::Builder::new()
         .thread_name("extractor-thread-".to_string())
         .num_threads(args.parallelism())
         .build();
-    let extractor = Arc::new(ContentExtractor::new());
-
-    if is_sample {
-        process_sample(
-            &queries,
-            extractor,
-            pool,
-            total_ids,
-            args.unique,
-            args.parallelism(),
-        )?;
+    let (list_limiter, download_limiter) = args.rate_limiters();
+    let mut extractor = ContentExtractor::new()
+        .with_paper_source(args.paper_source(list_limiter.clone(), download_limiter.clone()));
+    if let Some(source_dir) = &args.source_dir {
+        info!("reading PDFs from {}", source_dir.display());
     } else {
-        process_all(&queries, extractor, pool, total_ids)?;
+        info!("limiting GCS to {} listing(s) and {} download(s) a second", rate_label(args.list_rate), rate_label(args.download_rate));
     }
+    if let Some(cache_dir) = &args.pdf_cache {
+        let cache = PdfCache::open(cache_dir)?
+            .with_max_bytes(args.pdf_cache_max_mib.map(mib_to_bytes));
+        info!("using PDF cache at {}", cache_dir.display());
+        extractor = extractor.with_pdf_cache(cache);
+    }
+    let pipeline = ExtractionPipeline::new(extractor, pool);
 
-    info!("starting to commit extraction results...");
-    txn.commit()?;
-
-    info!("successfully committed transaction to update paper contents");
-
-    Ok(())
-}
+    if args.from_cache {
+        info!("going to re-extract {} paper(s) from stored text", store.count_papers_with_text()?);
+        pipeline.process_cached(&store, ID_PAGE_SIZE)?;
+        info!("finished updating paper contents");
+        return Ok(());
+    }
 
-fn process_all(
-    queries: &ArxivDBQueries,
-    extractor: Arc<ContentExtractor>,
-    pool: ThreadPool,
-    total_ids: u64,
-) -> AppResult<()> {
-    for page in page_iter(total_ids, 10) {
-        info!("processing page {}", page);
-
-        let ids = match queries.select_arxiv_ids(page) {
-            Ok(ids) => {
-                debug!("successfully got ids from db");
-                ids
-            }
-            Err(err) => {
-                error!("error encountered while fetching ids from db: {}", err);
-                return Err(err);
-            }
-        };
-
-        let contents = extract_paper_contents(extractor.clone(), &pool, ids)?;
-
-        // wait for all content to be extracted
-        info!("waiting on content extractors...");
-        pool.join();
-        info!("all done");
-
-        for item in contents {
-            match item {
-                Ok(content) => {
-                    info!("inserting content for {}", &content.id);
-                    queries.update_keywords_and_content(content)?;
-                }
-                Err(err) => {
-                    error!(
-                        "error while extracting content from {}: {}",
-                        err.id(),
-                        err.app_err()
-                    );
-                }
-            }
+    if args.pending {
+        let pending = store.count_pending_arxiv_ids(args.max_attempts)?;
+        info!("going to process {} paper(s) not yet extracted successfully", pending);
+        pipeline.process_pages(&store, store.pending_arxiv_id_pages(ID_PAGE_SIZE, args.max_attempts))?;
+    } else if !args.retry_status.is_empty() {
+        let candidates = store.count_retry_candidates(&args.retry_status, args.max_attempts)?;
+        info!("going to retry {} paper(s) with status {}", candidates, args.retry_status.join(","));
+        pipeline.process_pages(&store, store.retry_candidate_pages(ID_PAGE_SIZE, &args.retry_status, args.max_attempts))?;
+    } else if let Some(strategy) = args.strategy {
+        let current_version = args.outdated.then_some(EXTRACTOR_VERSION);
+        let candidates = store.count_arxiv_ids_by_keyword_strategy(strategy, current_version)?;
+        match current_version {
+            Some(current_version) => info!("going to re-extract {} paper(s) found by {} with an extractor other than {}", candidates, strategy, current_version),
+            None => info!("going to re-extract {} paper(s) found by {}", candidates, strategy),
         }
+        pipeline.process_pages(&store, store.keyword_strategy_pages(ID_PAGE_SIZE, strategy, current_version))?;
+    } else if let Some(sample_size) = args.count {
+        info!("going to process random sample of size {}", sample_size);
+        let ids = PaperStore::sample_arxiv_ids(&store, sample_size, args.unique)?;
+        let pages = ids.chunks(ID_PAGE_SIZE as usize)
+            .map(|page| Ok(page.to_vec()));
+        pipeline.process_pages(&store, pages)?;
+    } else {
+        info!("going to process all {} id(s)", store.count_arxiv_ids()?);
+        pipeline.process_pages(&store, store.arxiv_id_pages(ID_PAGE_SIZE))?;
     }
 
-    Ok(())
-}
-
-fn process_sample(
-    queries: &ArxivDBQueries,
-    extractor: Arc<ContentExtractor>,
-    pool: ThreadPool,
-    sample_size: u64,
-    unique: bool,
-    batch_size: usize,
-) -> AppResult<()> {
-    let ids = if unique {
-        queries.sample_arxiv_ids_unprocessed(sample_size)
-    } else {
-        queries.sample_arxiv_ids(sample_size)
-    }?;
-
-    // process ids in batches
-    for batch in ids.chunks(batch_size) {
-        let id_batch = batch.to_vec();
-        let results = extract_paper_contents(extractor.clone(), &pool, id_batch)?;
-        for item in results {
-            match item {
-                Ok(content) => {
-                    info!("inserting content for {}", &content.id);
-                    // update the status
-                    queries.insert_extraction_result(&content.id, None)?;
-                    // insert the content
-                    queries.update_keywords_and_content(content)?;
-                }
-                Err(err) => {
-                    error!(
-                        "error while extracting content from {}: {}",
-                        err.id(),
-                        err.app_err()
-                    );
-                    // just log that we had some kind of error
-                    queries.insert_extraction_result("", Some(err))?;
-                }
-            }
+    info!("finished updating paper contents");
+    for limiter in list_limiter.iter().chain(download_limiter.iter()) {
+        if limiter.stats().requests > 0 {
+            info!("{} budget: {}", limiter.name(), limiter.stats());
         }
     }
 
     Ok(())
 }
 
-fn extract_paper_contents(
-    extractor: Arc<ContentExtractor>,
-    pool: &ThreadPool,
-    ids: Vec<String>,
-) -> AppResult<Vec<ExtractResult<ArxivPaperContent>>> {
-    let (sender, recv) = std::sync::mpsc::channel::<ExtractResult<ArxivPaperContent>>();
-
-    for id in ids {
-        let extractor = extractor.clone();
-        let send = sender.clone();
-        pool.execute(move || {
-            debug!("starting to extract content for {}", id);
-            let result = extractor.fetch_and_extract_content(id);
-            send.send(result).unwrap();
-        })
-    }
+fn keyword_strategy_parser() -> impl TypedValueParser<Value=KeywordStrategy> {
+    PossibleValuesParser::new(["headers", "small_label", "index_terms_period", "index_terms_headers"])
+        .map(|value| value.parse::<KeywordStrategy>().expect("possible values are valid strategies"))
+}
 
-    drop(sender);
+fn parse_rate(value: &str) -> Result<f64, String> {
+    let rate = value.parse::<f64>().map_err(|err| err.to_string())?;
+    if !rate.is_finite() || rate < 0.0 {
+        return Err(format!("rate must be a non-negative number of requests per second, got {}", value));
+    }
+    if rate > 0.0 && rate < MIN_RATE {
+        return Err(format!("rate must be 0 for no limit or at least {} requests per second, got {}", MIN_RATE, value));
+    }
 
-    pool.join();
+    Ok(rate)
+}
 
-    let items = recv.iter().collect::<Vec<_>>();
-    Ok(items)
+fn rate_label(rate: f64) -> String {
+    if rate > 0.0 {
+        rate.to_string()
+    } else {
+        "unlimited".to_string()
+    }
 }
@@ -5,16 +5,19 @@ use std::path::{Path, PathBuf};
 use clap_derive::{Args, ValueEnum};
 use indicatif::ProgressBar;
 use log::{error, info};
-use keyword_dataset_rs::db::ArxivDB;
-use keyword_dataset_rs::db::pages::page_iter;
 use keyword_dataset_rs::err::AppResult;
+use keyword_dataset_rs::storage::PaperStore;
 use keyword_dataset_rs::training::TrainingRecord;
 use crate::subcommand::db::DBBaseArgs;
 use crate::subcommand::pull_data::writer::{NdJsonOutputFormatter, OutputFormatter};
 
+/// training records fetched per query
+const TRAINING_PAGE_SIZE: u64 = 500;
+
 #[derive(ValueEnum, Debug, Clone)]
 pub enum OutputFormat {
-    NDJSON
+    #[clap(name = "ndjson")]
+    NdJson
 }
 
 #[derive(Args, Debug)]
@@ -27,10 +30,13 @@ pub struct PullDataArgs {
     /// format of output data
     #[arg(short, long, default_value = "ndjson")]
     format: OutputFormat,
+    /// only pull papers matching this full-text query (FTS5 syntax, same as `search`)
+    #[arg(long)]
+    search: Option<String>,
 }
 
 pub(crate) fn pull_data(args: PullDataArgs) -> AppResult<()> {
-    let db = match ArxivDB::open(&args.db.db) {
+    let db = match args.db.open_db() {
         Ok(db) => {
             info!("successfully connected to db {}", args.db.db.display());
             db
@@ -41,20 +47,18 @@ pub(crate) fn pull_data(args: PullDataArgs) -> AppResult<()> {
         }
     };
 
-    db.execute_ddl()?;
-    db.turn_off_synchronous()?;
+    db.require_current_schema()?;
 
     let queries = db.queries();
 
-    let total_training_records = queries.count_training_data()?;
+    let total_training_records = queries.count_training_data(args.search.as_deref())?;
     info!("pulling {} training record(s)...", total_training_records);
     
     let pg = ProgressBar::new(total_training_records);
 
     let mut output_formatter = create_output_formatter(&args)?;
-    for page in page_iter(total_training_records, 10) {
-        let records = queries.select_training_data(page)?;
-        for record in records {
+    for records in queries.training_data_pages(TRAINING_PAGE_SIZE, args.search.as_deref()) {
+        for record in records? {
             let record = TrainingRecord::from(record);
             output_formatter.write_record(record)?;
             pg.inc(1);
@@ -69,7 +73,7 @@ pub(crate) fn pull_data(args: PullDataArgs) -> AppResult<()> {
 
 fn create_output_formatter(args: &PullDataArgs) -> AppResult<Box<dyn OutputFormatter>> {
     match args.format {
-        OutputFormat::NDJSON => {
+        OutputFormat::NdJson => {
             if let Some(output_path) = args.output.as_ref() {
                 let file = open_output_file(output_path)?;
                 Ok(NdJsonOutputFormatter::new(file).into_boxed_trait())
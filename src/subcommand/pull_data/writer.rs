@@ -34,10 +34,10 @@ impl<OutputStream: Write> Write for NdJsonOutputFormatter<OutputStream> {
 
 impl<OutputStream: Write> OutputFormatter for NdJsonOutputFormatter<OutputStream> {
     fn write_record(&mut self, record: TrainingRecord) -> AppResult<()> {
-        const NEWLINE: [u8; 1] = ['\n' as u8];
+        const NEWLINE: [u8; 1] = [b'\n'];
         let bytes = serde_json::to_vec(&record)?;
         self.write_all(&bytes)?;
-        self.write(&NEWLINE)?;
+        self.write_all(&NEWLINE)?;
         Ok(())
     }
 }
@@ -0,0 +1,56 @@
+use clap_derive::{Args, ValueEnum};
+use std::io::{BufWriter, Write};
+use keyword_dataset_rs::err::AppResult;
+use crate::subcommand::db::DBBaseArgs;
+
+#[derive(ValueEnum, Debug, Clone)]
+pub enum SearchOutputFormat {
+    /// one block per hit with its snippet
+    Text,
+    /// one JSON object per hit, per line
+    Json,
+}
+
+#[derive(Args, Debug)]
+pub struct SearchArgs {
+    #[clap(flatten)]
+    db: DBBaseArgs,
+    /// FTS5 query. Terms are ANDed; use quotes for phrases, OR/NOT/NEAR for more, and a column
+    /// prefix (title, abstract, content, keywords) to search one field, e.g.
+    /// `keywords: "federated learning"`
+    query: String,
+    /// maximum number of hits to return
+    #[arg(short = 'n', long, default_value_t = 20)]
+    limit: u64,
+    /// format of the results
+    #[arg(short, long, value_enum, default_value = "text")]
+    format: SearchOutputFormat,
+}
+
+pub(crate) fn search(args: SearchArgs) -> AppResult<()> {
+    let db = args.db.open_db()?;
+    db.require_current_schema()?;
+
+    let hits = db.queries().search_papers(&args.query, args.limit)?;
+
+    let mut out = BufWriter::new(std::io::stdout().lock());
+    match args.format {
+        SearchOutputFormat::Text => {
+            for hit in &hits {
+                writeln!(out, "{}  {}", hit.arxiv_id, hit.title.as_deref().unwrap_or("(untitled)"))?;
+                writeln!(out, "    {}", hit.snippet.split_whitespace().collect::<Vec<_>>().join(" "))?;
+                writeln!(out)?;
+            }
+            writeln!(out, "{} hit(s)", hits.len())?;
+        }
+        SearchOutputFormat::Json => {
+            for hit in &hits {
+                serde_json::to_writer(&mut out, hit)?;
+                writeln!(out)?;
+            }
+        }
+    }
+
+    out.flush()?;
+    Ok(())
+}
@@ -9,16 +9,6 @@ pub struct TrainingRecord {
     pub keywords: Vec<String>,
 }
 
-impl TrainingRecord {
-    pub fn parse_keywords(keywords: String) -> Vec<String> {
-        keywords
-            .split(",")
-            .map(|term| term.trim())
-            .map(strip_internal_newlines)
-            .collect()
-    }
-}
-
 fn strip_internal_newlines(term: &str) -> String {
     term.chars()
         .filter(|ch| *ch != '\n')
@@ -31,7 +21,9 @@ impl From<ArxivPaperContentEntity> for TrainingRecord {
             arxiv_id: value.id,
             content: value.paper_content,
             abstract_content: value.abstract_text,
-            keywords: TrainingRecord::parse_keywords(value.keywords),
+            keywords: value.keywords.iter()
+                .map(|term| strip_internal_newlines(term))
+                .collect(),
         }
     }
 }